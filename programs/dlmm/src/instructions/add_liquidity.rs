@@ -1,9 +1,13 @@
-use crate::state::{BinArray, LbPair, Position};
+use std::cell::RefMut;
+
+use crate::instructions::swap::load_bin_arrays;
+use crate::math::{
+    get_liquidity, is_bin_id_in_range, mul_div, price_from_bin_id, Rounding, BASIS_POINT_MAX,
+};
+use crate::state::{Bin, BinArray, LbPair, Position};
 use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Token, TokenAccount, Transfer};
 
-pub const BASIS_POINT_MAX: u64 = 10000;
-
 #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
 pub struct BinLiquidityDistribution {
     pub delta_id: i32, // Offset from active bin (-1, 0, +1, etc.)
@@ -13,9 +17,14 @@ pub struct BinLiquidityDistribution {
 
 #[derive(Accounts)]
 pub struct AddLiquidity<'info> {
-    #[account(mut)]
+    #[account(
+        mut,
+        constraint = lb_pair.is_deposit_enabled() @ ErrorCode::DepositDisabled
+    )]
     pub lb_pair: Account<'info, LbPair>,
 
+    /// First bin array deposited into. Further arrays, for entries landing
+    /// outside this one, are passed as remaining accounts.
     #[account(
         mut,
         constraint = bin_array.load()?.lb_pair == lb_pair.key()
@@ -23,13 +32,14 @@ pub struct AddLiquidity<'info> {
     pub bin_array: AccountLoader<'info, BinArray>,
 
     #[account(
-        init_if_needed,
-        payer = user,
-        space = 8 + Position::LEN,
-        seeds = [b"position", lb_pair.key().as_ref(), user.key().as_ref()],
-        bump
+        mut,
+        constraint = position.load()?.lb_pair == lb_pair.key(),
+        constraint = position.load()?.is_authorized(&user.key(), position_token.as_deref())
     )]
-    pub position: Box<Account<'info, Position>>,
+    pub position: AccountLoader<'info, Position>,
+
+    /// The user's position token account, required once the position is tokenized.
+    pub position_token: Option<Account<'info, TokenAccount>>,
 
     #[account(
         mut,
@@ -47,71 +57,168 @@ pub struct AddLiquidity<'info> {
 
     #[account(
         mut,
-        constraint = reserve_x.mint == lb_pair.token_x_mint,
+        address = lb_pair.reserve_x_vault
     )]
     pub reserve_x: Box<Account<'info, TokenAccount>>,
 
     #[account(
         mut,
-        constraint = reserve_y.mint == lb_pair.token_y_mint,
+        address = lb_pair.reserve_y_vault
     )]
     pub reserve_y: Box<Account<'info, TokenAccount>>,
 
     #[account(mut)]
     pub user: Signer<'info>,
     pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
 }
 
-pub fn handler(
-    ctx: Context<AddLiquidity>,
+pub(crate) fn handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, AddLiquidity<'info>>,
     amount_x: u64,
     amount_y: u64,
     bin_liquidity_dist: Vec<BinLiquidityDistribution>,
 ) -> Result<()> {
+    let extra_bin_arrays = load_bin_arrays(&ctx.accounts.lb_pair.key(), ctx.remaining_accounts)?;
+
     let lb_pair = &mut ctx.accounts.lb_pair;
-    let mut bin_array = ctx.accounts.bin_array.load_mut()?;
-    let position = &mut ctx.accounts.position;
+    let mut bin_arrays = vec![ctx.accounts.bin_array.load_mut()?];
+    for bin_array in extra_bin_arrays.iter() {
+        bin_arrays.push(bin_array.load_mut()?);
+    }
+    let mut position = ctx.accounts.position.load_mut()?;
 
-    let mut total_x_deposited: u64 = 0;
-    let mut total_y_deposited: u64 = 0;
+    let (total_x_deposited, total_y_deposited) = deposit(
+        lb_pair,
+        &mut bin_arrays,
+        &mut position,
+        amount_x,
+        amount_y,
+        &bin_liquidity_dist,
+    )?;
 
-    if position.lb_pair == Pubkey::default() {
-        position.lb_pair = lb_pair.key();
-        position.owner = ctx.accounts.user.key();
+    if total_x_deposited > 0 {
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.user_token_x.to_account_info(),
+            to: ctx.accounts.reserve_x.to_account_info(),
+            authority: ctx.accounts.user.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+        token::transfer(cpi_ctx, total_x_deposited)?;
+    }
+
+    if total_y_deposited > 0 {
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.user_token_y.to_account_info(),
+            to: ctx.accounts.reserve_y.to_account_info(),
+            authority: ctx.accounts.user.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+        token::transfer(cpi_ctx, total_y_deposited)?;
     }
 
+    lb_pair.reserve_x = lb_pair
+        .reserve_x
+        .checked_add(total_x_deposited)
+        .ok_or(ErrorCode::Overflow)?;
+    lb_pair.reserve_y = lb_pair
+        .reserve_y
+        .checked_add(total_y_deposited)
+        .ok_or(ErrorCode::Overflow)?;
+
+    msg!(
+        "Add liquidity complete: {} X, {} Y",
+        total_x_deposited,
+        total_y_deposited
+    );
+
+    Ok(())
+}
+
+/// Deposits `amount_x` and `amount_y` into the position's bins as laid out by
+/// `bin_liquidity_dist` and returns the amounts actually taken, never more
+/// than `amount_x` and `amount_y`. Token transfers and the pair's reserve
+/// totals are left to the caller.
+pub fn deposit(
+    lb_pair: &LbPair,
+    bin_arrays: &mut [RefMut<BinArray>],
+    position: &mut Position,
+    amount_x: u64,
+    amount_y: u64,
+    bin_liquidity_dist: &[BinLiquidityDistribution],
+) -> Result<(u64, u64)> {
+    let total_dist_x: u64 = bin_liquidity_dist
+        .iter()
+        .map(|dist| dist.dist_x as u64)
+        .sum();
+    let total_dist_y: u64 = bin_liquidity_dist
+        .iter()
+        .map(|dist| dist.dist_y as u64)
+        .sum();
+    require!(
+        total_dist_x <= BASIS_POINT_MAX && total_dist_y <= BASIS_POINT_MAX,
+        ErrorCode::InvalidDistribution
+    );
+
+    let mut total_x_deposited: u64 = 0;
+    let mut total_y_deposited: u64 = 0;
+
     for dist in bin_liquidity_dist.iter() {
-        let target_bin_id = (lb_pair.active_bin_id as i32) + dist.delta_id;
+        let target_bin_id = lb_pair
+            .active_bin_id
+            .checked_add(dist.delta_id)
+            .filter(|bin_id| is_bin_id_in_range(*bin_id, lb_pair.bin_step))
+            .ok_or(ErrorCode::BinOutOfRange)?;
+
+        // Bins below the active one hold only X and bins above it only Y.
+        require!(
+            dist.dist_x == 0 || target_bin_id <= lb_pair.active_bin_id,
+            ErrorCode::InvalidBinComposition
+        );
+        require!(
+            dist.dist_y == 0 || target_bin_id >= lb_pair.active_bin_id,
+            ErrorCode::InvalidBinComposition
+        );
 
-        let base_bin_id = (bin_array.index as i32) * 70;
-        let bin_index = (target_bin_id - base_bin_id) as usize;
-        require!(bin_index < 70, ErrorCode::BinOutOfRange);
+        let bin = bin_arrays
+            .iter_mut()
+            .find_map(|bin_array| bin_array.bin_mut(target_bin_id))
+            .ok_or(ErrorCode::BinOutOfRange)?;
 
-        let bin = &mut bin_array.bins[bin_index];
+        let bin_index = position
+            .bin_index(target_bin_id)
+            .ok_or(ErrorCode::BinOutsidePosition)?;
 
         let deposit_x = (amount_x as u128 * dist.dist_x as u128 / BASIS_POINT_MAX as u128) as u64;
         let deposit_y = (amount_y as u128 * dist.dist_y as u128 / BASIS_POINT_MAX as u128) as u64;
 
+        // Only the part matching the bin's current composition is taken; the
+        // rest is never transferred, rather than being donated to the bin.
+        let (deposit_x, deposit_y) =
+            matched_deposit(bin, deposit_x, deposit_y).ok_or(ErrorCode::Overflow)?;
+        if deposit_x == 0 && deposit_y == 0 {
+            continue;
+        }
+
+        // Shares track price-weighted liquidity, so a bin can be funded with
+        // one token and later deposits are valued at the bin's own price.
+        let price =
+            price_from_bin_id(target_bin_id, lb_pair.bin_step).ok_or(ErrorCode::Overflow)?;
+        let liquidity = get_liquidity(deposit_x, deposit_y, price).ok_or(ErrorCode::Overflow)?;
         let shares: u128 = if bin.total_shares == 0 {
-            let product = (deposit_x as u128) * (deposit_y as u128);
-            integer_sqrt(product)
+            liquidity
         } else {
-            let shares_x = if bin.reserve_x > 0 {
-                (deposit_x as u128) * bin.total_shares / (bin.reserve_x as u128)
-            } else {
-                u128::MAX
-            };
-            let shares_y = if bin.reserve_y > 0 {
-                (deposit_y as u128) * bin.total_shares / (bin.reserve_y as u128)
-            } else {
-                u128::MAX
-            };
-            shares_x.min(shares_y)
+            let bin_liquidity =
+                get_liquidity(bin.reserve_x, bin.reserve_y, price).ok_or(ErrorCode::Overflow)?;
+            mul_div(liquidity, bin.total_shares, bin_liquidity, Rounding::Down)
+                .ok_or(ErrorCode::Overflow)?
         };
 
         require!(shares > 0, ErrorCode::InsufficientLiquidity);
 
+        position
+            .update_fees(bin_index, bin)
+            .ok_or(ErrorCode::Overflow)?;
+
         bin.reserve_x = bin
             .reserve_x
             .checked_add(deposit_x)
@@ -129,13 +236,6 @@ pub fn handler(
             .checked_add(shares)
             .ok_or(ErrorCode::Overflow)?;
 
-        if target_bin_id < position.lower_bin_id {
-            position.lower_bin_id = target_bin_id;
-        }
-        if target_bin_id > position.upper_bin_id {
-            position.upper_bin_id = target_bin_id;
-        }
-
         total_x_deposited += deposit_x;
         total_y_deposited += deposit_y;
 
@@ -148,63 +248,201 @@ pub fn handler(
         );
     }
 
-    if total_x_deposited > 0 {
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.user_token_x.to_account_info(),
-            to: ctx.accounts.reserve_x.to_account_info(),
-            authority: ctx.accounts.user.to_account_info(),
-        };
-        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
-        token::transfer(cpi_ctx, total_x_deposited)?;
-    }
-
-    if total_y_deposited > 0 {
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.user_token_y.to_account_info(),
-            to: ctx.accounts.reserve_y.to_account_info(),
-            authority: ctx.accounts.user.to_account_info(),
-        };
-        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
-        token::transfer(cpi_ctx, total_y_deposited)?;
-    }
-
-    lb_pair.reserve_x = lb_pair
-        .reserve_x
-        .checked_add(total_x_deposited)
-        .ok_or(ErrorCode::Overflow)?;
-    lb_pair.reserve_y = lb_pair
-        .reserve_y
-        .checked_add(total_y_deposited)
-        .ok_or(ErrorCode::Overflow)?;
-
-    msg!(
-        "Add liquidity complete: {} X, {} Y",
-        total_x_deposited,
-        total_y_deposited
-    );
-
-    Ok(())
+    Ok((total_x_deposited, total_y_deposited))
 }
 
-fn integer_sqrt(n: u128) -> u128 {
-    if n == 0 {
-        return 0;
-    }
-    let mut x = n;
-    let mut y = (x + 1) / 2;
-    while y < x {
-        x = y;
-        y = (x + n / x) / 2;
+/// Largest part of a deposit that keeps the bin's ratio of X to Y. An empty
+/// bin takes the deposit as is.
+fn matched_deposit(bin: &Bin, amount_x: u64, amount_y: u64) -> Option<(u64, u64)> {
+    match (bin.reserve_x, bin.reserve_y) {
+        (0, 0) => Some((amount_x, amount_y)),
+        (_, 0) => Some((amount_x, 0)),
+        (0, _) => Some((0, amount_y)),
+        (reserve_x, reserve_y) => {
+            let x_for_y = mul_div(
+                amount_y as u128,
+                reserve_x as u128,
+                reserve_y as u128,
+                Rounding::Down,
+            )?;
+            if x_for_y <= amount_x as u128 {
+                Some((x_for_y as u64, amount_y))
+            } else {
+                let y_for_x = mul_div(
+                    amount_x as u128,
+                    reserve_y as u128,
+                    reserve_x as u128,
+                    Rounding::Down,
+                )?;
+                Some((amount_x, y_for_x as u64))
+            }
+        }
     }
-    x
 }
 
 #[error_code]
-pub enum ErrorCode {
-    #[msg("Bin index out of range for this bin array")]
+pub(crate) enum ErrorCode {
+    #[msg("Bin not covered by the provided bin arrays")]
     BinOutOfRange,
     #[msg("Insufficient liquidity to mint shares")]
     InsufficientLiquidity,
     #[msg("Arithmetic overflow")]
     Overflow,
+    #[msg("Bin is outside the position's range")]
+    BinOutsidePosition,
+    #[msg("X can only go at or below the active bin and Y at or above it")]
+    InvalidBinComposition,
+    #[msg("Deposits are disabled on this pair")]
+    DepositDisabled,
+    #[msg("Distribution weights add up to more than 10000 basis points")]
+    InvalidDistribution,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytemuck::Zeroable;
+    use std::cell::RefCell;
+
+    fn bin(reserve_x: u64, reserve_y: u64) -> Bin {
+        let mut bin = Bin::zeroed();
+        bin.reserve_x = reserve_x;
+        bin.reserve_y = reserve_y;
+        bin
+    }
+
+    fn lb_pair(active_bin_id: i32) -> LbPair {
+        LbPair {
+            token_x_mint: Pubkey::default(),
+            token_y_mint: Pubkey::default(),
+            reserve_x: 0,
+            reserve_y: 0,
+            bin_step: 100,
+            active_bin_id,
+            base_free_rate: 0,
+            protocol_fee_rate: 0,
+            volatility_accumulator: 0,
+            last_update_timestamp: 0,
+            bump: 0,
+            volatility_reference: 0,
+            index_reference: active_bin_id,
+            filter_period: 0,
+            decay_period: 0,
+            reduction_factor: 0,
+            variable_fee_control: 0,
+            max_volatility_accumulator: 0,
+            protocol_fee_x: 0,
+            protocol_fee_y: 0,
+            reserve_x_vault: Pubkey::default(),
+            reserve_y_vault: Pubkey::default(),
+            status: 0,
+        }
+    }
+
+    #[test]
+    fn matched_deposit_into_empty_bin_takes_everything() {
+        assert_eq!(matched_deposit(&bin(0, 0), 100, 50), Some((100, 50)));
+        assert_eq!(matched_deposit(&bin(0, 0), 0, 0), Some((0, 0)));
+    }
+
+    #[test]
+    fn matched_deposit_into_one_sided_bin_takes_that_side() {
+        assert_eq!(matched_deposit(&bin(1000, 0), 100, 50), Some((100, 0)));
+        assert_eq!(matched_deposit(&bin(1000, 0), 0, 50), Some((0, 0)));
+        assert_eq!(matched_deposit(&bin(0, 1000), 100, 50), Some((0, 50)));
+        assert_eq!(matched_deposit(&bin(0, 1000), 100, 0), Some((0, 0)));
+    }
+
+    #[test]
+    fn matched_deposit_into_mixed_bin_keeps_its_ratio() {
+        // Two X per Y: Y is the scarcer side of the first deposit, X of the second.
+        assert_eq!(matched_deposit(&bin(1000, 500), 300, 100), Some((200, 100)));
+        assert_eq!(matched_deposit(&bin(1000, 500), 100, 100), Some((100, 50)));
+        // The matched side rounds down.
+        assert_eq!(matched_deposit(&bin(1000, 300), 10, 1), Some((3, 1)));
+        assert_eq!(matched_deposit(&bin(1000, 300), 0, 10), Some((0, 0)));
+    }
+
+    #[test]
+    fn deposit_skips_bins_that_match_nothing() {
+        // After a swap crossed bin 0 the active bin 1 holds only Y, so X aimed
+        // at it matches nothing while bin 0 still takes X.
+        let lb_pair = lb_pair(1);
+        let bin_array = RefCell::new(BinArray::zeroed());
+        {
+            let mut bin_array = bin_array.borrow_mut();
+            let active_bin = bin_array.bin_mut(1).unwrap();
+            active_bin.reserve_y = 1000;
+            active_bin.total_shares = 1000;
+        }
+        let mut position = Position::zeroed();
+        position.lower_bin_id = 0;
+        position.upper_bin_id = 1;
+        let dist = [
+            BinLiquidityDistribution {
+                delta_id: -1,
+                dist_x: 5000,
+                dist_y: 0,
+            },
+            BinLiquidityDistribution {
+                delta_id: 0,
+                dist_x: 5000,
+                dist_y: 0,
+            },
+        ];
+
+        let mut bin_arrays = vec![bin_array.borrow_mut()];
+        let deposited = deposit(&lb_pair, &mut bin_arrays, &mut position, 1000, 0, &dist).unwrap();
+        assert_eq!(deposited, (500, 0));
+        assert_eq!(position.liquidity_shares[0], 500);
+        assert_eq!(position.liquidity_shares[1], 0);
+        assert_eq!(bin_arrays[0].bin(0).unwrap().reserve_x, 500);
+        assert_eq!(bin_arrays[0].bin(1).unwrap().reserve_x, 0);
+    }
+
+    #[test]
+    fn deposit_rejects_weights_above_basis_point_max() {
+        let lb_pair = lb_pair(0);
+        let bin_array = RefCell::new(BinArray::zeroed());
+        let mut position = Position::zeroed();
+        position.upper_bin_id = 1;
+        let dist = |dist_x, dist_y| {
+            [
+                BinLiquidityDistribution {
+                    delta_id: 0,
+                    dist_x,
+                    dist_y,
+                },
+                BinLiquidityDistribution {
+                    delta_id: 1,
+                    dist_x: 0,
+                    dist_y,
+                },
+            ]
+        };
+
+        let mut bin_arrays = vec![bin_array.borrow_mut()];
+        for (dist_x, dist_y) in [(10001, 0), (0, 5001)] {
+            let result = deposit(
+                &lb_pair,
+                &mut bin_arrays,
+                &mut position,
+                1000,
+                1000,
+                &dist(dist_x, dist_y),
+            );
+            assert_eq!(result.unwrap_err(), ErrorCode::InvalidDistribution.into());
+        }
+
+        let deposited = deposit(
+            &lb_pair,
+            &mut bin_arrays,
+            &mut position,
+            1000,
+            1000,
+            &dist(10000, 5000),
+        )
+        .unwrap();
+        assert_eq!(deposited, (1000, 1000));
+    }
 }
@@ -0,0 +1,248 @@
+use crate::instructions::add_liquidity::{self, AddLiquidity, BinLiquidityDistribution};
+use crate::math::{is_bin_id_in_range, BASIS_POINT_MAX};
+use crate::state::BINS_PER_ARRAY;
+use anchor_lang::prelude::*;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StrategyType {
+    /// Same weight in every bin.
+    Spot,
+    /// Weight falls off linearly away from the active bin.
+    Curve,
+    /// Weight grows linearly away from the active bin.
+    BidAsk,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct StrategyParameters {
+    pub min_bin_id: i32,
+    pub max_bin_id: i32,
+    pub strategy_type: StrategyType,
+}
+
+/// Deposits `amount_x` over the range's bins at or below the active bin and
+/// `amount_y` over those at or above it, shaped by the strategy.
+pub(crate) fn handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, AddLiquidity<'info>>,
+    amount_x: u64,
+    amount_y: u64,
+    strategy: StrategyParameters,
+) -> Result<()> {
+    let lb_pair = &ctx.accounts.lb_pair;
+    let bin_liquidity_dist = strategy_distribution(
+        lb_pair.active_bin_id,
+        lb_pair.bin_step,
+        amount_x,
+        amount_y,
+        &strategy,
+    )?;
+
+    add_liquidity::handler(ctx, amount_x, amount_y, bin_liquidity_dist)
+}
+
+/// Turns a strategy into the per-bin distribution `add_liquidity` takes, in
+/// basis points of each amount. Rounding dust goes to the bin nearest the
+/// active bin on each side.
+pub fn strategy_distribution(
+    active_bin_id: i32,
+    bin_step: u16,
+    amount_x: u64,
+    amount_y: u64,
+    strategy: &StrategyParameters,
+) -> Result<Vec<BinLiquidityDistribution>> {
+    let StrategyParameters {
+        min_bin_id,
+        max_bin_id,
+        strategy_type,
+    } = *strategy;
+    // Bounding both ends first keeps the deltas below from overflowing.
+    require!(
+        is_bin_id_in_range(min_bin_id, bin_step)
+            && is_bin_id_in_range(max_bin_id, bin_step)
+            && min_bin_id <= max_bin_id
+            && max_bin_id - min_bin_id < BINS_PER_ARRAY as i32,
+        ErrorCode::InvalidStrategyRange
+    );
+
+    let x_bins = min_bin_id..=max_bin_id.min(active_bin_id);
+    let y_bins = min_bin_id.max(active_bin_id)..=max_bin_id;
+    require!(
+        amount_x == 0 || !x_bins.is_empty(),
+        ErrorCode::NoBinsForAmount
+    );
+    require!(
+        amount_y == 0 || !y_bins.is_empty(),
+        ErrorCode::NoBinsForAmount
+    );
+
+    let dist_x = side_distribution(active_bin_id, x_bins, strategy_type);
+    let dist_y = side_distribution(active_bin_id, y_bins, strategy_type);
+
+    let mut bin_liquidity_dist: Vec<BinLiquidityDistribution> = Vec::new();
+    for bin_id in min_bin_id..=max_bin_id {
+        let dist_x = dist_x
+            .iter()
+            .find(|(id, _)| *id == bin_id)
+            .map_or(0, |(_, dist)| *dist);
+        let dist_y = dist_y
+            .iter()
+            .find(|(id, _)| *id == bin_id)
+            .map_or(0, |(_, dist)| *dist);
+        let dist_x = if amount_x > 0 { dist_x } else { 0 };
+        let dist_y = if amount_y > 0 { dist_y } else { 0 };
+        if dist_x == 0 && dist_y == 0 {
+            continue;
+        }
+        bin_liquidity_dist.push(BinLiquidityDistribution {
+            delta_id: bin_id - active_bin_id,
+            dist_x,
+            dist_y,
+        });
+    }
+
+    Ok(bin_liquidity_dist)
+}
+
+/// Splits `BASIS_POINT_MAX` over one side's bins by strategy weight.
+fn side_distribution(
+    active_bin_id: i32,
+    bins: std::ops::RangeInclusive<i32>,
+    strategy_type: StrategyType,
+) -> Vec<(i32, u16)> {
+    if bins.is_empty() {
+        return Vec::new();
+    }
+
+    let distance = |bin_id: i32| bin_id.abs_diff(active_bin_id) as u64;
+    let max_distance = distance(*bins.start()).max(distance(*bins.end()));
+    let weights: Vec<(i32, u64)> = bins
+        .map(|bin_id| {
+            let weight = match strategy_type {
+                StrategyType::Spot => 1,
+                StrategyType::Curve => max_distance + 1 - distance(bin_id),
+                StrategyType::BidAsk => distance(bin_id) + 1,
+            };
+            (bin_id, weight)
+        })
+        .collect();
+    let total_weight: u64 = weights.iter().map(|(_, weight)| weight).sum();
+
+    let mut dist: Vec<(i32, u16)> = weights
+        .iter()
+        .map(|(bin_id, weight)| (*bin_id, (weight * BASIS_POINT_MAX / total_weight) as u16))
+        .collect();
+
+    let assigned: u64 = dist.iter().map(|(_, bps)| *bps as u64).sum();
+    if let Some(nearest) = dist.iter_mut().min_by_key(|(bin_id, _)| distance(*bin_id)) {
+        nearest.1 += (BASIS_POINT_MAX - assigned) as u16;
+    }
+
+    dist
+}
+
+#[error_code]
+pub(crate) enum ErrorCode {
+    #[msg("Strategy range is empty or wider than a position")]
+    InvalidStrategyRange,
+    #[msg("No bin in the range can hold this token")]
+    NoBinsForAmount,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BIN_STEP: u16 = 100;
+
+    fn distribution(
+        active_bin_id: i32,
+        min_bin_id: i32,
+        max_bin_id: i32,
+        strategy_type: StrategyType,
+    ) -> Vec<BinLiquidityDistribution> {
+        let strategy = StrategyParameters {
+            min_bin_id,
+            max_bin_id,
+            strategy_type,
+        };
+        strategy_distribution(active_bin_id, BIN_STEP, 1000, 1000, &strategy).unwrap()
+    }
+
+    fn sides(dist: &[BinLiquidityDistribution]) -> (Vec<u16>, Vec<u16>) {
+        (
+            dist.iter().map(|bin| bin.dist_x).collect(),
+            dist.iter().map(|bin| bin.dist_y).collect(),
+        )
+    }
+
+    #[test]
+    fn every_shape_sums_to_basis_point_max_per_side() {
+        for strategy_type in [
+            StrategyType::Spot,
+            StrategyType::Curve,
+            StrategyType::BidAsk,
+        ] {
+            for (min_bin_id, max_bin_id) in [(-3, 3), (-6, 0), (0, 6), (-1, 68), (0, 0)] {
+                let dist = distribution(0, min_bin_id, max_bin_id, strategy_type);
+                let (x, y) = sides(&dist);
+                let sum = |side: &[u16]| side.iter().map(|bps| *bps as u64).sum::<u64>();
+                if min_bin_id <= 0 {
+                    assert_eq!(sum(&x), BASIS_POINT_MAX, "{strategy_type:?} x");
+                }
+                if max_bin_id >= 0 {
+                    assert_eq!(sum(&y), BASIS_POINT_MAX, "{strategy_type:?} y");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn shapes_weight_bins_by_distance() {
+        // X goes to bins -2..=0 and Y to 0..=2, so both sides meet in the active bin.
+        let (x, y) = sides(&distribution(0, -2, 2, StrategyType::Spot));
+        assert_eq!(x, [3333, 3333, 3334, 0, 0]);
+        assert_eq!(y, [0, 0, 3334, 3333, 3333]);
+
+        let (x, y) = sides(&distribution(0, -2, 2, StrategyType::Curve));
+        assert_eq!(x, [1666, 3333, 5001, 0, 0]);
+        assert_eq!(y, [0, 0, 5001, 3333, 1666]);
+
+        let (x, y) = sides(&distribution(0, -2, 2, StrategyType::BidAsk));
+        assert_eq!(x, [5000, 3333, 1667, 0, 0]);
+        assert_eq!(y, [0, 0, 1667, 3333, 5000]);
+    }
+
+    #[test]
+    fn deltas_are_relative_to_the_active_bin() {
+        let dist = distribution(10, 8, 12, StrategyType::Spot);
+        let deltas: Vec<i32> = dist.iter().map(|bin| bin.delta_id).collect();
+        assert_eq!(deltas, [-2, -1, 0, 1, 2]);
+    }
+
+    #[test]
+    fn rejects_bins_outside_the_price_range() {
+        let strategy = StrategyParameters {
+            min_bin_id: i32::MIN,
+            max_bin_id: i32::MIN + 10,
+            strategy_type: StrategyType::Spot,
+        };
+        assert!(strategy_distribution(0, BIN_STEP, 1000, 1000, &strategy).is_err());
+
+        let strategy = StrategyParameters {
+            min_bin_id: i32::MAX - 10,
+            max_bin_id: i32::MAX,
+            strategy_type: StrategyType::Curve,
+        };
+        assert!(strategy_distribution(0, BIN_STEP, 1000, 1000, &strategy).is_err());
+    }
+
+    #[test]
+    fn rejects_ranges_wider_than_a_position() {
+        let strategy = StrategyParameters {
+            min_bin_id: 0,
+            max_bin_id: BINS_PER_ARRAY as i32,
+            strategy_type: StrategyType::Spot,
+        };
+        assert!(strategy_distribution(0, BIN_STEP, 1000, 1000, &strategy).is_err());
+    }
+}
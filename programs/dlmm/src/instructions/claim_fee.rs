@@ -0,0 +1,143 @@
+use crate::instructions::swap::load_bin_arrays;
+use crate::state::{BinArray, LbPair, Position};
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+
+#[derive(Accounts)]
+pub struct ClaimFee<'info> {
+    #[account(mut)]
+    pub lb_pair: Account<'info, LbPair>,
+
+    #[account(constraint = bin_array.load()?.lb_pair == lb_pair.key())]
+    pub bin_array: AccountLoader<'info, BinArray>,
+
+    #[account(
+        mut,
+        constraint = position.load()?.lb_pair == lb_pair.key(),
+        constraint = position.load()?.is_authorized(&user.key(), position_token.as_deref())
+    )]
+    pub position: AccountLoader<'info, Position>,
+
+    /// The user's position token account, required once the position is tokenized.
+    pub position_token: Option<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = user_token_x.owner == user.key(),
+        constraint = user_token_x.mint == lb_pair.token_x_mint,
+    )]
+    pub user_token_x: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = user_token_y.owner == user.key(),
+        constraint = user_token_y.mint == lb_pair.token_y_mint,
+    )]
+    pub user_token_y: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        address = lb_pair.reserve_x_vault
+    )]
+    pub reserve_x: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        address = lb_pair.reserve_y_vault
+    )]
+    pub reserve_y: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+/// Harvests the fees of every bin the position holds shares in. A position
+/// spanning two bin arrays passes the second one as a remaining account.
+pub(crate) fn handler<'info>(ctx: Context<'_, '_, 'info, 'info, ClaimFee<'info>>) -> Result<()> {
+    let extra_bin_arrays = load_bin_arrays(&ctx.accounts.lb_pair.key(), ctx.remaining_accounts)?;
+
+    let lb_pair = &mut ctx.accounts.lb_pair;
+    let mut bin_arrays = vec![ctx.accounts.bin_array.load()?];
+    for bin_array in extra_bin_arrays.iter() {
+        bin_arrays.push(bin_array.load()?);
+    }
+    let mut position = ctx.accounts.position.load_mut()?;
+
+    for bin_id in position.lower_bin_id..=position.upper_bin_id {
+        let bin_index = position.bin_index(bin_id).ok_or(ErrorCode::BinOutOfRange)?;
+        if position.liquidity_shares[bin_index] == 0 {
+            continue;
+        }
+        let bin = bin_arrays
+            .iter()
+            .find_map(|bin_array| bin_array.bin(bin_id))
+            .ok_or(ErrorCode::BinOutOfRange)?;
+        position
+            .update_fees(bin_index, bin)
+            .ok_or(ErrorCode::Overflow)?;
+    }
+
+    let fee_x = position.fee_x_pending;
+    let fee_y = position.fee_y_pending;
+    position.fee_x_pending = 0;
+    position.fee_y_pending = 0;
+
+    let seeds = &[
+        b"lb_pair",
+        lb_pair.token_x_mint.as_ref(),
+        lb_pair.token_y_mint.as_ref(),
+        &lb_pair.bin_step.to_le_bytes(),
+        &[lb_pair.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    if fee_x > 0 {
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.reserve_x.to_account_info(),
+            to: ctx.accounts.user_token_x.to_account_info(),
+            authority: lb_pair.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        );
+        token::transfer(cpi_ctx, fee_x)?;
+    }
+
+    if fee_y > 0 {
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.reserve_y.to_account_info(),
+            to: ctx.accounts.user_token_y.to_account_info(),
+            authority: lb_pair.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        );
+        token::transfer(cpi_ctx, fee_y)?;
+    }
+
+    lb_pair.reserve_x = lb_pair
+        .reserve_x
+        .checked_sub(fee_x)
+        .ok_or(ErrorCode::Overflow)?;
+    lb_pair.reserve_y = lb_pair
+        .reserve_y
+        .checked_sub(fee_y)
+        .ok_or(ErrorCode::Overflow)?;
+
+    msg!("Claimed fees: {} X, {} Y", fee_x, fee_y);
+
+    Ok(())
+}
+
+#[error_code]
+pub(crate) enum ErrorCode {
+    #[msg("Arithmetic overflow")]
+    Overflow,
+    #[msg("A bin of the position is not in the given bin arrays")]
+    BinOutOfRange,
+}
@@ -0,0 +1,45 @@
+use crate::state::Position;
+use anchor_lang::prelude::*;
+use anchor_spl::token::TokenAccount;
+
+#[derive(Accounts)]
+pub struct ClosePosition<'info> {
+    #[account(
+        mut,
+        close = user,
+        constraint = position.load()?.is_authorized(&user.key(), position_token.as_deref())
+    )]
+    pub position: AccountLoader<'info, Position>,
+
+    /// The user's position token account, required once the position is tokenized.
+    pub position_token: Option<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+/// Closes an emptied position and refunds its rent to the user. All shares
+/// must be withdrawn and all fees claimed first.
+pub(crate) fn handler(ctx: Context<ClosePosition>) -> Result<()> {
+    let position = ctx.accounts.position.load()?;
+    require!(
+        position.liquidity_shares.iter().all(|shares| *shares == 0),
+        ErrorCode::PositionNotEmpty
+    );
+    require!(
+        position.fee_x_pending == 0 && position.fee_y_pending == 0,
+        ErrorCode::UnclaimedFees
+    );
+
+    msg!("Position {} closed", ctx.accounts.position.key());
+
+    Ok(())
+}
+
+#[error_code]
+pub(crate) enum ErrorCode {
+    #[msg("Position still holds liquidity")]
+    PositionNotEmpty,
+    #[msg("Position has unclaimed fees")]
+    UnclaimedFees,
+}
@@ -1,4 +1,5 @@
-use crate::state::{Bin, BinArray, LbPair};
+use crate::math::{max_bin_id, min_bin_id};
+use crate::state::{BinArray, LbPair, BINS_PER_ARRAY};
 use anchor_lang::prelude::*;
 
 #[derive(Accounts)]
@@ -22,20 +23,33 @@ pub struct InitializeBinArray<'info> {
     pub system_program: Program<'info, System>,
 }
 
-pub fn handler(ctx: Context<InitializeBinArray>, index: i32) -> Result<()> {
+pub(crate) fn handler(ctx: Context<InitializeBinArray>, index: i32) -> Result<()> {
+    let bin_step = ctx.accounts.lb_pair.bin_step;
+    let base_bin_id = BinArray::first_bin_id(index);
+    let last_bin_id = base_bin_id + BINS_PER_ARRAY as i64 - 1;
+    require!(
+        last_bin_id >= min_bin_id(bin_step) as i64 && base_bin_id <= max_bin_id(bin_step) as i64,
+        ErrorCode::BinArrayIndexOutOfRange
+    );
+
     let mut bin_array = ctx.accounts.bin_array.load_init()?;
     bin_array.lb_pair = ctx.accounts.lb_pair.key();
-    bin_array.index = index as u16;
+    bin_array.index = index;
     bin_array.bump = ctx.bumps.bin_array;
-    let base_bin_id = (index as u32) * 70;
     for (i, bins) in bin_array.bins.iter_mut().enumerate() {
         bins.reserve_x = 0;
         bins.reserve_y = 0;
         bins.fee_x_per_share = 0;
         bins.fee_y_per_share = 0;
-        bins.bin_id = (base_bin_id + i as u32) as u16;
+        bins.bin_id = (base_bin_id + i as i64) as i32;
         bins.total_shares = 0;
     }
     msg!("Bin array initialised");
     Ok(())
 }
+
+#[error_code]
+pub(crate) enum ErrorCode {
+    #[msg("Bin array index is outside the bin id range for this bin step")]
+    BinArrayIndexOutOfRange,
+}
@@ -0,0 +1,49 @@
+use crate::program::Dlmm;
+use crate::state::GlobalConfig;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct InitializeGlobalConfig<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + GlobalConfig::LEN,
+        seeds = [b"global_config"],
+        bump
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, Dlmm>,
+
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(admin.key())
+            @ ErrorCode::Unauthorized
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Creates the program's single config account. Only the program's upgrade
+/// authority may call it; it becomes the admin, and the guardian until
+/// `set_guardian` names another.
+pub(crate) fn handler(ctx: Context<InitializeGlobalConfig>) -> Result<()> {
+    let global_config = &mut ctx.accounts.global_config;
+    global_config.admin = ctx.accounts.admin.key();
+    global_config.guardian = ctx.accounts.admin.key();
+    global_config.bump = ctx.bumps.global_config;
+
+    msg!("Global config initialized, admin {}", global_config.admin);
+
+    Ok(())
+}
+
+#[error_code]
+pub(crate) enum ErrorCode {
+    #[msg("Signer is not the program's upgrade authority")]
+    Unauthorized,
+}
@@ -1,31 +1,121 @@
+use crate::math::is_bin_id_in_range;
+use crate::state::{LbPair, PairRegistry, PresetParameter, MAX_PAIRS_PER_REGISTRY};
 use anchor_lang::prelude::*;
-use anchor_spl::token::Mint;
-use crate::state::LbPair;
+use anchor_spl::token::{Mint, Token, TokenAccount};
 
 #[derive(Accounts)]
-pub struct InitializeLbPair<'info>{
+pub struct InitializeLbPair<'info> {
     #[account(
         init,
         payer = user,
         space = LbPair::LEN,
-        seeds = [b"lb_pair", token_x_mint.key().as_ref(), token_y_mint.key().as_ref()],
+        seeds = [
+            b"lb_pair",
+            token_x_mint.key().as_ref(),
+            token_y_mint.key().as_ref(),
+            &preset_parameter.bin_step.to_le_bytes()
+        ],
         bump
     )]
     pub lb_pair: Account<'info, LbPair>,
 
+    /// Fee tier the pair is created from.
+    pub preset_parameter: Account<'info, PresetParameter>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + PairRegistry::LEN,
+        seeds = [b"pair_registry", token_x_mint.key().as_ref(), token_y_mint.key().as_ref()],
+        bump
+    )]
+    pub pair_registry: Box<Account<'info, PairRegistry>>,
+
+    #[account(
+        init,
+        payer = user,
+        seeds = [b"reserve", lb_pair.key().as_ref(), token_x_mint.key().as_ref()],
+        bump,
+        token::mint = token_x_mint,
+        token::authority = lb_pair,
+    )]
+    pub reserve_x: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        init,
+        payer = user,
+        seeds = [b"reserve", lb_pair.key().as_ref(), token_y_mint.key().as_ref()],
+        bump,
+        token::mint = token_y_mint,
+        token::authority = lb_pair,
+    )]
+    pub reserve_y: Box<Account<'info, TokenAccount>>,
+
     #[account(mut)]
     pub user: Signer<'info>,
-    pub token_x_mint: Account<'info, Mint>,
-    pub token_y_mint: Account<'info, Mint>,
-    pub system_program: Program<'info, System>
+    pub token_x_mint: Box<Account<'info, Mint>>,
+    pub token_y_mint: Box<Account<'info, Mint>>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
 }
 
-pub fn handler(ctx: Context<InitializeLbPair>, bin_step: u16)->Result<()>{
+/// Creates a pair with the bin step and fee parameters of `preset_parameter`,
+/// along with the reserve vaults it owns. Trading starts at `active_id`, so
+/// the initial price is `price_from_bin_id(active_id, bin_step)`.
+///
+/// Mints must be passed in ascending order, so a token pair has one pair per
+/// bin step rather than one per ordering, and each is listed in the token
+/// pair's registry.
+pub(crate) fn handler(ctx: Context<InitializeLbPair>, active_id: i32) -> Result<()> {
+    require!(
+        ctx.accounts.token_x_mint.key() < ctx.accounts.token_y_mint.key(),
+        ErrorCode::InvalidTokenOrder
+    );
+    let preset_parameter = &ctx.accounts.preset_parameter;
+    require!(
+        is_bin_id_in_range(active_id, preset_parameter.bin_step),
+        ErrorCode::InvalidActiveId
+    );
+
     let lb_pair = &mut ctx.accounts.lb_pair;
     lb_pair.token_x_mint = ctx.accounts.token_x_mint.key();
     lb_pair.token_y_mint = ctx.accounts.token_y_mint.key();
-    lb_pair.bin_step = bin_step;
-    lb_pair.active_bin_id = 0;
+    lb_pair.bin_step = preset_parameter.bin_step;
+    lb_pair.active_bin_id = active_id;
+    lb_pair.base_free_rate = preset_parameter.base_fee_rate;
+    lb_pair.protocol_fee_rate = preset_parameter.protocol_fee_rate;
+    lb_pair.index_reference = active_id;
+    lb_pair.filter_period = preset_parameter.filter_period;
+    lb_pair.decay_period = preset_parameter.decay_period;
+    lb_pair.reduction_factor = preset_parameter.reduction_factor;
+    lb_pair.variable_fee_control = preset_parameter.variable_fee_control;
+    lb_pair.max_volatility_accumulator = preset_parameter.max_volatility_accumulator;
+    lb_pair.last_update_timestamp = Clock::get()?.unix_timestamp as u64;
+    lb_pair.reserve_x_vault = ctx.accounts.reserve_x.key();
+    lb_pair.reserve_y_vault = ctx.accounts.reserve_y.key();
     lb_pair.bump = ctx.bumps.lb_pair;
+
+    let pair_registry = &mut ctx.accounts.pair_registry;
+    if pair_registry.lb_pairs.is_empty() {
+        pair_registry.token_x_mint = lb_pair.token_x_mint;
+        pair_registry.token_y_mint = lb_pair.token_y_mint;
+        pair_registry.bump = ctx.bumps.pair_registry;
+    }
+    require!(
+        pair_registry.lb_pairs.len() < MAX_PAIRS_PER_REGISTRY,
+        ErrorCode::RegistryFull
+    );
+    pair_registry.lb_pairs.push(lb_pair.key());
+
     Ok(())
-}
\ No newline at end of file
+}
+
+#[error_code]
+pub(crate) enum ErrorCode {
+    #[msg("Active bin id is outside the bin step's range")]
+    InvalidActiveId,
+    #[msg("Token X mint must sort before token Y mint")]
+    InvalidTokenOrder,
+    #[msg("Token pair already has the maximum number of pairs")]
+    RegistryFull,
+}
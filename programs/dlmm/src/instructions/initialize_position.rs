@@ -0,0 +1,65 @@
+use crate::math::is_bin_id_in_range;
+use crate::state::{LbPair, Position, BINS_PER_ARRAY};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct InitializePosition<'info> {
+    pub lb_pair: Account<'info, LbPair>,
+
+    /// A fresh keypair, signing to create its account.
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + Position::LEN
+    )]
+    pub position: AccountLoader<'info, Position>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Opens a new, empty position over `width` bins starting at `lower_bin_id`.
+/// Each position is its own keypair account, so opening one never touches
+/// state shared with other positions on the pair.
+pub(crate) fn handler(
+    ctx: Context<InitializePosition>,
+    lower_bin_id: i32,
+    width: u32,
+) -> Result<()> {
+    let bin_step = ctx.accounts.lb_pair.bin_step;
+    require!(
+        width > 0 && width as usize <= BINS_PER_ARRAY,
+        ErrorCode::InvalidPositionWidth
+    );
+    let upper_bin_id = lower_bin_id
+        .checked_add(width as i32 - 1)
+        .ok_or(ErrorCode::InvalidPositionRange)?;
+    require!(
+        is_bin_id_in_range(lower_bin_id, bin_step) && is_bin_id_in_range(upper_bin_id, bin_step),
+        ErrorCode::InvalidPositionRange
+    );
+
+    let mut position = ctx.accounts.position.load_init()?;
+    position.lb_pair = ctx.accounts.lb_pair.key();
+    position.owner = ctx.accounts.owner.key();
+    position.lower_bin_id = lower_bin_id;
+    position.upper_bin_id = upper_bin_id;
+
+    msg!(
+        "Position {} initialized over bins {} to {}",
+        ctx.accounts.position.key(),
+        lower_bin_id,
+        upper_bin_id
+    );
+
+    Ok(())
+}
+
+#[error_code]
+pub(crate) enum ErrorCode {
+    #[msg("Position width must be between 1 and 70 bins")]
+    InvalidPositionWidth,
+    #[msg("Position range is outside the pair's bin range")]
+    InvalidPositionRange,
+}
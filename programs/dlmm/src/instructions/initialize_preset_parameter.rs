@@ -0,0 +1,132 @@
+use crate::math::{variable_fee_rate, BASIS_POINT_MAX, FEE_PRECISION, MAX_FEE_RATE};
+use crate::state::{GlobalConfig, PresetParameter, MAX_PRESET_PARAMETERS};
+use anchor_lang::prelude::*;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct InitPresetParameterArgs {
+    pub bin_step: u16,
+    /// Basis points.
+    pub base_fee_rate: u64,
+    pub filter_period: u16,
+    pub decay_period: u16,
+    pub reduction_factor: u16,
+    pub variable_fee_control: u32,
+    pub max_volatility_accumulator: u64,
+    /// Basis points of the swap fee.
+    pub protocol_fee_rate: u64,
+}
+
+#[derive(Accounts)]
+#[instruction(args: InitPresetParameterArgs)]
+pub struct InitializePresetParameter<'info> {
+    #[account(
+        mut,
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        constraint = global_config.admin == admin.key() @ ErrorCode::Unauthorized
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + PresetParameter::LEN,
+        seeds = [
+            b"preset_parameter".as_ref(),
+            args.bin_step.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub preset_parameter: Account<'info, PresetParameter>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Publishes a fee tier that pairs can be created from. A tier is identified
+/// by its bin step alone; its parameters cannot change afterwards. At most
+/// `MAX_PRESET_PARAMETERS` tiers can exist.
+pub(crate) fn handler(
+    ctx: Context<InitializePresetParameter>,
+    args: InitPresetParameterArgs,
+) -> Result<()> {
+    require!(
+        (ctx.accounts.global_config.preset_count as usize) < MAX_PRESET_PARAMETERS,
+        ErrorCode::TooManyPresets
+    );
+    require!(
+        args.bin_step > 0 && args.bin_step as u64 <= BASIS_POINT_MAX,
+        ErrorCode::InvalidBinStep
+    );
+    require!(
+        args.base_fee_rate > 0
+            && args.base_fee_rate <= MAX_FEE_RATE / (FEE_PRECISION / BASIS_POINT_MAX),
+        ErrorCode::InvalidBaseFee
+    );
+    require!(
+        args.filter_period < args.decay_period,
+        ErrorCode::InvalidVolatilityPeriods
+    );
+    require!(
+        args.reduction_factor as u64 <= BASIS_POINT_MAX,
+        ErrorCode::InvalidReductionFactor
+    );
+    require!(
+        args.protocol_fee_rate <= BASIS_POINT_MAX,
+        ErrorCode::InvalidProtocolFeeRate
+    );
+    // The accumulator is capped at its maximum, so the variable fee there is
+    // the most a pair on this tier can ever compute.
+    let max_variable_fee_rate = variable_fee_rate(
+        args.max_volatility_accumulator,
+        args.bin_step,
+        args.variable_fee_control,
+    );
+    require!(
+        max_variable_fee_rate.is_some_and(|fee_rate| fee_rate <= MAX_FEE_RATE),
+        ErrorCode::InvalidVariableFee
+    );
+
+    let preset_parameter = &mut ctx.accounts.preset_parameter;
+    preset_parameter.bin_step = args.bin_step;
+    preset_parameter.base_fee_rate = args.base_fee_rate;
+    preset_parameter.filter_period = args.filter_period;
+    preset_parameter.decay_period = args.decay_period;
+    preset_parameter.reduction_factor = args.reduction_factor;
+    preset_parameter.variable_fee_control = args.variable_fee_control;
+    preset_parameter.max_volatility_accumulator = args.max_volatility_accumulator;
+    preset_parameter.protocol_fee_rate = args.protocol_fee_rate;
+    preset_parameter.bump = ctx.bumps.preset_parameter;
+
+    let global_config = &mut ctx.accounts.global_config;
+    global_config.preset_count += 1;
+
+    msg!(
+        "Preset initialized: bin step {}, base fee {} bps",
+        args.bin_step,
+        args.base_fee_rate
+    );
+
+    Ok(())
+}
+
+#[error_code]
+pub(crate) enum ErrorCode {
+    #[msg("Signer is not the admin")]
+    Unauthorized,
+    #[msg("Bin step must be between 1 and 10000 basis points")]
+    InvalidBinStep,
+    #[msg("Base fee must be between 1 and 1000 basis points")]
+    InvalidBaseFee,
+    #[msg("Filter period must be shorter than decay period")]
+    InvalidVolatilityPeriods,
+    #[msg("Reduction factor cannot exceed 10000 basis points")]
+    InvalidReductionFactor,
+    #[msg("Protocol fee rate cannot exceed 10000 basis points")]
+    InvalidProtocolFeeRate,
+    #[msg("Variable fee at the maximum volatility accumulator exceeds the maximum fee rate")]
+    InvalidVariableFee,
+    #[msg("The maximum number of presets already exists")]
+    TooManyPresets,
+}
@@ -1,14 +1,59 @@
 pub mod add_liquidity;
 pub use add_liquidity::*;
 
+pub mod add_liquidity_by_strategy;
+pub use add_liquidity_by_strategy::*;
+
+pub mod claim_fee;
+pub use claim_fee::*;
+
+pub mod close_position;
+pub use close_position::*;
+
 pub mod initialize_bin;
 pub use initialize_bin::*;
 
+pub mod initialize_global_config;
+pub use initialize_global_config::*;
+
 pub mod initialize_lbpair;
 pub use initialize_lbpair::*;
 
+pub mod initialize_position;
+pub use initialize_position::*;
+
+pub mod initialize_preset_parameter;
+pub use initialize_preset_parameter::*;
+
+pub mod rebalance_position;
+pub use rebalance_position::*;
+
 pub mod remove_liquidity;
 pub use remove_liquidity::*;
 
+pub mod remove_liquidity_by_range;
+
+pub mod set_admin;
+pub use set_admin::*;
+
+pub mod set_guardian;
+pub use set_guardian::*;
+
+pub mod set_pair_status;
+pub use set_pair_status::*;
+
 pub mod swap;
 pub use swap::*;
+
+pub mod swap_exact_out;
+
+pub mod swap_with_limit;
+
+pub mod tokenize_position;
+pub use tokenize_position::*;
+
+pub mod transfer_position_owner;
+pub use transfer_position_owner::*;
+
+pub mod withdraw_protocol_fee;
+pub use withdraw_protocol_fee::*;
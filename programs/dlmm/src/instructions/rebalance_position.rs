@@ -0,0 +1,198 @@
+use crate::instructions::add_liquidity::{deposit, BinLiquidityDistribution};
+use crate::instructions::remove_liquidity::{withdraw, BinLiquidityReduction, RemoveLiquidity};
+use crate::instructions::swap::{apply_quote, load_bin_arrays};
+use crate::math::is_bin_id_in_range;
+use crate::quote::{quote_exact_in, SwapLimit};
+use crate::state::BinArray;
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Transfer};
+
+/// Swap run between the withdrawal and the redeposit, paid out of the
+/// withdrawn tokens.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct RebalanceSwap {
+    pub amount_in: u64,
+    pub min_amount_out: u64,
+    pub swap_for_y: bool,
+}
+
+/// Withdraws `bin_liquidity_removal`, optionally moves the emptied position to
+/// start at `new_lower_bin_id`, optionally swaps part of the proceeds, and
+/// redeposits them by `bin_liquidity_dist` around the resulting active bin.
+/// Distribution weights are basis points of the tokens on hand after the
+/// swap. Whatever the redeposit does not take is paid out to the user.
+pub(crate) fn handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, RemoveLiquidity<'info>>,
+    bin_liquidity_removal: Vec<BinLiquidityReduction>,
+    new_lower_bin_id: Option<i32>,
+    swap: Option<RebalanceSwap>,
+    bin_liquidity_dist: Vec<BinLiquidityDistribution>,
+) -> Result<()> {
+    // Shares `RemoveLiquidity`, which stays open in every status, but redeposits.
+    require!(
+        ctx.accounts.lb_pair.is_deposit_enabled(),
+        ErrorCode::DepositDisabled
+    );
+    require!(
+        swap.is_none() || ctx.accounts.lb_pair.is_swap_enabled(),
+        ErrorCode::SwapDisabled
+    );
+    let extra_bin_arrays = load_bin_arrays(&ctx.accounts.lb_pair.key(), ctx.remaining_accounts)?;
+
+    let lb_pair = &mut ctx.accounts.lb_pair;
+    let mut bin_arrays = vec![ctx.accounts.bin_array.load_mut()?];
+    for bin_array in extra_bin_arrays.iter() {
+        bin_arrays.push(bin_array.load_mut()?);
+    }
+    let mut position = ctx.accounts.position.load_mut()?;
+
+    let (mut amount_x, mut amount_y) =
+        withdraw(&mut bin_arrays, &mut position, &bin_liquidity_removal)?;
+
+    if let Some(new_lower_bin_id) = new_lower_bin_id {
+        // Slots are relative to the lower bin, so only an empty position may move.
+        require!(
+            position.liquidity_shares.iter().all(|shares| *shares == 0),
+            ErrorCode::PositionNotEmpty
+        );
+        let new_upper_bin_id = new_lower_bin_id
+            .checked_add(position.upper_bin_id - position.lower_bin_id)
+            .ok_or(ErrorCode::InvalidPositionRange)?;
+        require!(
+            is_bin_id_in_range(new_lower_bin_id, lb_pair.bin_step)
+                && is_bin_id_in_range(new_upper_bin_id, lb_pair.bin_step),
+            ErrorCode::InvalidPositionRange
+        );
+        position.lower_bin_id = new_lower_bin_id;
+        position.upper_bin_id = new_upper_bin_id;
+    }
+
+    if let Some(swap) = swap {
+        let available = if swap.swap_for_y { amount_x } else { amount_y };
+        require!(
+            swap.amount_in <= available,
+            ErrorCode::SwapExceedsWithdrawal
+        );
+
+        let current_timestamp = Clock::get()?.unix_timestamp as u64;
+        let quote = {
+            let snapshots: Vec<&BinArray> =
+                bin_arrays.iter().map(|bin_array| &**bin_array).collect();
+            quote_exact_in(
+                lb_pair,
+                &snapshots,
+                swap.amount_in,
+                swap.swap_for_y,
+                SwapLimit::default(),
+                current_timestamp,
+            )?
+        };
+        require!(
+            quote.amount_out >= swap.min_amount_out,
+            ErrorCode::SlippageExceeded
+        );
+        apply_quote(lb_pair, &mut bin_arrays, &quote, current_timestamp)?;
+
+        // The swapped tokens never leave the reserves.
+        if swap.swap_for_y {
+            amount_x -= quote.amount_in;
+            amount_y = amount_y
+                .checked_add(quote.amount_out)
+                .ok_or(ErrorCode::Overflow)?;
+        } else {
+            amount_y -= quote.amount_in;
+            amount_x = amount_x
+                .checked_add(quote.amount_out)
+                .ok_or(ErrorCode::Overflow)?;
+        }
+    }
+
+    let (deposited_x, deposited_y) = deposit(
+        lb_pair,
+        &mut bin_arrays,
+        &mut position,
+        amount_x,
+        amount_y,
+        &bin_liquidity_dist,
+    )?;
+    let refund_x = amount_x
+        .checked_sub(deposited_x)
+        .ok_or(ErrorCode::Overflow)?;
+    let refund_y = amount_y
+        .checked_sub(deposited_y)
+        .ok_or(ErrorCode::Overflow)?;
+
+    let seeds = &[
+        b"lb_pair",
+        lb_pair.token_x_mint.as_ref(),
+        lb_pair.token_y_mint.as_ref(),
+        &lb_pair.bin_step.to_le_bytes(),
+        &[lb_pair.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    if refund_x > 0 {
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.reserve_x.to_account_info(),
+            to: ctx.accounts.user_token_x.to_account_info(),
+            authority: lb_pair.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        );
+        token::transfer(cpi_ctx, refund_x)?;
+    }
+
+    if refund_y > 0 {
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.reserve_y.to_account_info(),
+            to: ctx.accounts.user_token_y.to_account_info(),
+            authority: lb_pair.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        );
+        token::transfer(cpi_ctx, refund_y)?;
+    }
+
+    lb_pair.reserve_x = lb_pair
+        .reserve_x
+        .checked_sub(refund_x)
+        .ok_or(ErrorCode::Overflow)?;
+    lb_pair.reserve_y = lb_pair
+        .reserve_y
+        .checked_sub(refund_y)
+        .ok_or(ErrorCode::Overflow)?;
+
+    msg!(
+        "Rebalance complete: {} X, {} Y redeposited, {} X, {} Y returned",
+        deposited_x,
+        deposited_y,
+        refund_x,
+        refund_y
+    );
+
+    Ok(())
+}
+
+#[error_code]
+pub(crate) enum ErrorCode {
+    #[msg("Only an empty position can move its range")]
+    PositionNotEmpty,
+    #[msg("Position range is outside the pair's bin range")]
+    InvalidPositionRange,
+    #[msg("Swap input exceeds the withdrawn amount")]
+    SwapExceedsWithdrawal,
+    #[msg("Slippage exceeded")]
+    SlippageExceeded,
+    #[msg("Arithmetic overflow")]
+    Overflow,
+    #[msg("Deposits are disabled on this pair")]
+    DepositDisabled,
+    #[msg("Swaps are disabled on this pair")]
+    SwapDisabled,
+}
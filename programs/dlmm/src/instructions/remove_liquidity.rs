@@ -1,3 +1,6 @@
+use std::cell::RefMut;
+
+use crate::instructions::swap::load_bin_arrays;
 use crate::state::{BinArray, LbPair, Position};
 use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Token, TokenAccount, Transfer};
@@ -13,6 +16,8 @@ pub struct RemoveLiquidity<'info> {
     #[account(mut)]
     pub lb_pair: Account<'info, LbPair>,
 
+    /// First bin array withdrawn from. Further arrays are passed as remaining
+    /// accounts.
     #[account(
         mut,
         constraint = bin_array.load()?.lb_pair == lb_pair.key()
@@ -21,10 +26,13 @@ pub struct RemoveLiquidity<'info> {
 
     #[account(
         mut,
-        constraint = position.lb_pair == lb_pair.key(),
-        constraint = position.owner == user.key()
+        constraint = position.load()?.lb_pair == lb_pair.key(),
+        constraint = position.load()?.is_authorized(&user.key(), position_token.as_deref())
     )]
-    pub position: Box<Account<'info, Position>>,
+    pub position: AccountLoader<'info, Position>,
+
+    /// The user's position token account, required once the position is tokenized.
+    pub position_token: Option<Account<'info, TokenAccount>>,
 
     #[account(
         mut,
@@ -42,15 +50,13 @@ pub struct RemoveLiquidity<'info> {
 
     #[account(
         mut,
-        constraint = reserve_x.mint == lb_pair.token_x_mint,
-        constraint = reserve_x.owner == lb_pair.key()
+        address = lb_pair.reserve_x_vault
     )]
     pub reserve_x: Box<Account<'info, TokenAccount>>,
 
     #[account(
         mut,
-        constraint = reserve_y.mint == lb_pair.token_y_mint,
-        constraint = reserve_y.owner == lb_pair.key()
+        address = lb_pair.reserve_y_vault
     )]
     pub reserve_y: Box<Account<'info, TokenAccount>>,
 
@@ -59,14 +65,85 @@ pub struct RemoveLiquidity<'info> {
     pub token_program: Program<'info, Token>,
 }
 
-pub fn handler(
-    ctx: Context<RemoveLiquidity>,
+pub(crate) fn handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, RemoveLiquidity<'info>>,
     bin_liquidity_removal: Vec<BinLiquidityReduction>,
 ) -> Result<()> {
+    let extra_bin_arrays = load_bin_arrays(&ctx.accounts.lb_pair.key(), ctx.remaining_accounts)?;
+
     let lb_pair = &mut ctx.accounts.lb_pair;
-    let mut bin_array = ctx.accounts.bin_array.load_mut()?;
-    let position = &mut ctx.accounts.position;
+    let mut bin_arrays = vec![ctx.accounts.bin_array.load_mut()?];
+    for bin_array in extra_bin_arrays.iter() {
+        bin_arrays.push(bin_array.load_mut()?);
+    }
+    let mut position = ctx.accounts.position.load_mut()?;
+
+    let (total_x_withdrawn, total_y_withdrawn) =
+        withdraw(&mut bin_arrays, &mut position, &bin_liquidity_removal)?;
+
+    let seeds = &[
+        b"lb_pair",
+        lb_pair.token_x_mint.as_ref(),
+        lb_pair.token_y_mint.as_ref(),
+        &lb_pair.bin_step.to_le_bytes(),
+        &[lb_pair.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    if total_x_withdrawn > 0 {
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.reserve_x.to_account_info(),
+            to: ctx.accounts.user_token_x.to_account_info(),
+            authority: lb_pair.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        );
+        token::transfer(cpi_ctx, total_x_withdrawn)?;
+    }
+
+    if total_y_withdrawn > 0 {
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.reserve_y.to_account_info(),
+            to: ctx.accounts.user_token_y.to_account_info(),
+            authority: lb_pair.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        );
+        token::transfer(cpi_ctx, total_y_withdrawn)?;
+    }
+
+    lb_pair.reserve_x = lb_pair
+        .reserve_x
+        .checked_sub(total_x_withdrawn)
+        .ok_or(ErrorCode::Overflow)?;
+    lb_pair.reserve_y = lb_pair
+        .reserve_y
+        .checked_sub(total_y_withdrawn)
+        .ok_or(ErrorCode::Overflow)?;
+
+    msg!(
+        "Remove liquidity complete: {} X, {} Y",
+        total_x_withdrawn,
+        total_y_withdrawn
+    );
+
+    Ok(())
+}
 
+/// Burns the given shares from the position's bins and returns the amounts
+/// released. Token transfers and the pair's reserve totals are left to the
+/// caller.
+pub fn withdraw(
+    bin_arrays: &mut [RefMut<BinArray>],
+    position: &mut Position,
+    bin_liquidity_removal: &[BinLiquidityReduction],
+) -> Result<(u64, u64)> {
     let mut total_x_withdrawn: u64 = 0;
     let mut total_y_withdrawn: u64 = 0;
 
@@ -74,11 +151,13 @@ pub fn handler(
         let bin_id = reduction.bin_id;
         let shares_to_burn = reduction.shares_to_burn;
 
-        let base_bin_id = (bin_array.index as i32) * 70;
-        let bin_index = (bin_id - base_bin_id) as usize;
-        require!(bin_index < 70, ErrorCode::BinOutOfRange);
-
-        let bin = &mut bin_array.bins[bin_index];
+        let bin_index = position
+            .bin_index(bin_id)
+            .ok_or(ErrorCode::BinOutsidePosition)?;
+        let bin = bin_arrays
+            .iter_mut()
+            .find_map(|bin_array| bin_array.bin_mut(bin_id))
+            .ok_or(ErrorCode::BinOutOfRange)?;
 
         require!(
             shares_to_burn <= bin.total_shares,
@@ -93,6 +172,10 @@ pub fn handler(
             continue;
         }
 
+        position
+            .update_fees(bin_index, bin)
+            .ok_or(ErrorCode::Overflow)?;
+
         let amount_x = (bin.reserve_x as u128)
             .checked_mul(shares_to_burn)
             .ok_or(ErrorCode::Overflow)?
@@ -134,64 +217,15 @@ pub fn handler(
         );
     }
 
-    let seeds = &[
-        b"lb_pair",
-        lb_pair.token_x_mint.as_ref(),
-        lb_pair.token_y_mint.as_ref(),
-        &[lb_pair.bump],
-    ];
-    let signer = &[&seeds[..]];
-
-    if total_x_withdrawn > 0 {
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.reserve_x.to_account_info(),
-            to: ctx.accounts.user_token_x.to_account_info(),
-            authority: lb_pair.to_account_info(),
-        };
-        let cpi_ctx = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            cpi_accounts,
-            signer,
-        );
-        token::transfer(cpi_ctx, total_x_withdrawn)?;
-    }
-
-    if total_y_withdrawn > 0 {
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.reserve_y.to_account_info(),
-            to: ctx.accounts.user_token_y.to_account_info(),
-            authority: lb_pair.to_account_info(),
-        };
-        let cpi_ctx = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            cpi_accounts,
-            signer,
-        );
-        token::transfer(cpi_ctx, total_y_withdrawn)?;
-    }
-
-    lb_pair.reserve_x = lb_pair
-        .reserve_x
-        .checked_sub(total_x_withdrawn)
-        .ok_or(ErrorCode::Overflow)?;
-    lb_pair.reserve_y = lb_pair
-        .reserve_y
-        .checked_sub(total_y_withdrawn)
-        .ok_or(ErrorCode::Overflow)?;
-
-    msg!(
-        "Remove liquidity complete: {} X, {} Y",
-        total_x_withdrawn,
-        total_y_withdrawn
-    );
-
-    Ok(())
+    Ok((total_x_withdrawn, total_y_withdrawn))
 }
 
 #[error_code]
-pub enum ErrorCode {
-    #[msg("Bin index out of range for this bin array")]
+pub(crate) enum ErrorCode {
+    #[msg("Bin not covered by the provided bin arrays")]
     BinOutOfRange,
+    #[msg("Bin is outside the position's range")]
+    BinOutsidePosition,
     #[msg("Insufficient liquidity in bin")]
     InsufficientLiquidity,
     #[msg("Insufficient shares in position")]
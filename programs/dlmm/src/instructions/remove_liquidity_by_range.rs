@@ -0,0 +1,58 @@
+use crate::instructions::remove_liquidity::{self, BinLiquidityReduction, RemoveLiquidity};
+use crate::math::{mul_div, Rounding, BASIS_POINT_MAX};
+use anchor_lang::prelude::*;
+
+/// Burns `bps` of the position's shares in every bin from `from_bin_id` to
+/// `to_bin_id`, reading the share balances on-chain.
+pub(crate) fn handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, RemoveLiquidity<'info>>,
+    from_bin_id: i32,
+    to_bin_id: i32,
+    bps: u16,
+) -> Result<()> {
+    require!(
+        bps > 0 && bps as u64 <= BASIS_POINT_MAX,
+        ErrorCode::InvalidBps
+    );
+    require!(from_bin_id <= to_bin_id, ErrorCode::InvalidRange);
+
+    let bin_liquidity_removal = {
+        let position = ctx.accounts.position.load()?;
+        let first = position
+            .bin_index(from_bin_id)
+            .ok_or(ErrorCode::InvalidRange)?;
+        let last = position
+            .bin_index(to_bin_id)
+            .ok_or(ErrorCode::InvalidRange)?;
+
+        let mut bin_liquidity_removal = Vec::new();
+        for (bin_id, shares) in (from_bin_id..).zip(&position.liquidity_shares[first..=last]) {
+            let shares_to_burn = mul_div(
+                *shares,
+                bps as u128,
+                BASIS_POINT_MAX as u128,
+                Rounding::Down,
+            )
+            .ok_or(ErrorCode::Overflow)?;
+            if shares_to_burn > 0 {
+                bin_liquidity_removal.push(BinLiquidityReduction {
+                    bin_id,
+                    shares_to_burn,
+                });
+            }
+        }
+        bin_liquidity_removal
+    };
+
+    remove_liquidity::handler(ctx, bin_liquidity_removal)
+}
+
+#[error_code]
+pub(crate) enum ErrorCode {
+    #[msg("Basis points must be between 1 and 10000")]
+    InvalidBps,
+    #[msg("Range is empty or outside the position")]
+    InvalidRange,
+    #[msg("Arithmetic overflow")]
+    Overflow,
+}
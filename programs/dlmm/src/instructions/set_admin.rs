@@ -0,0 +1,33 @@
+use crate::state::GlobalConfig;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct SetAdmin<'info> {
+    #[account(
+        mut,
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        constraint = global_config.admin == admin.key() @ ErrorCode::Unauthorized
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    pub admin: Signer<'info>,
+
+    /// CHECK: any account may become the admin.
+    pub new_admin: UncheckedAccount<'info>,
+}
+
+pub(crate) fn handler(ctx: Context<SetAdmin>) -> Result<()> {
+    let global_config = &mut ctx.accounts.global_config;
+    global_config.admin = ctx.accounts.new_admin.key();
+
+    msg!("Admin set to {}", global_config.admin);
+
+    Ok(())
+}
+
+#[error_code]
+pub(crate) enum ErrorCode {
+    #[msg("Signer is not the admin")]
+    Unauthorized,
+}
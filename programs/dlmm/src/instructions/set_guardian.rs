@@ -0,0 +1,33 @@
+use crate::state::GlobalConfig;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct SetGuardian<'info> {
+    #[account(
+        mut,
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        constraint = global_config.admin == admin.key() @ ErrorCode::Unauthorized
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    pub admin: Signer<'info>,
+
+    /// CHECK: any account may become the guardian.
+    pub new_guardian: UncheckedAccount<'info>,
+}
+
+pub(crate) fn handler(ctx: Context<SetGuardian>) -> Result<()> {
+    let global_config = &mut ctx.accounts.global_config;
+    global_config.guardian = ctx.accounts.new_guardian.key();
+
+    msg!("Guardian set to {}", global_config.guardian);
+
+    Ok(())
+}
+
+#[error_code]
+pub(crate) enum ErrorCode {
+    #[msg("Signer is not the admin")]
+    Unauthorized,
+}
@@ -0,0 +1,38 @@
+use crate::state::{GlobalConfig, LbPair, PAIR_STATUS_MASK};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct SetPairStatus<'info> {
+    #[account(mut)]
+    pub lb_pair: Account<'info, LbPair>,
+
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        constraint = global_config.is_operator(&authority.key()) @ ErrorCode::Unauthorized
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    pub authority: Signer<'info>,
+}
+
+/// Replaces the pair's `PAIR_STATUS_*` flags. Withdrawals and fee claims are
+/// never blocked, whatever the flags.
+pub(crate) fn handler(ctx: Context<SetPairStatus>, status: u8) -> Result<()> {
+    require!(status & !PAIR_STATUS_MASK == 0, ErrorCode::InvalidStatus);
+
+    let lb_pair = &mut ctx.accounts.lb_pair;
+    lb_pair.status = status;
+
+    msg!("Pair status set to {:#04b}", status);
+
+    Ok(())
+}
+
+#[error_code]
+pub(crate) enum ErrorCode {
+    #[msg("Signer is neither the admin nor the guardian")]
+    Unauthorized,
+    #[msg("Unknown status flags")]
+    InvalidStatus,
+}
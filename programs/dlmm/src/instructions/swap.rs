@@ -1,14 +1,25 @@
 use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Token, TokenAccount, Transfer};
 
+use std::cell::RefMut;
+
+use crate::quote::{quote_exact_in, SwapLimit, SwapQuote};
 use crate::state::{BinArray, LbPair};
 
 #[derive(Accounts)]
 pub struct Swap<'info> {
-    #[account(mut)]
+    #[account(
+        mut,
+        constraint = lb_pair.is_swap_enabled() @ ErrorCode::SwapDisabled
+    )]
     pub lb_pair: Account<'info, LbPair>,
 
-    #[account(mut)]
+    /// Bin array holding the active bin. Bin arrays the swap may cross into
+    /// are passed, in traversal order, as remaining accounts.
+    #[account(
+        mut,
+        constraint = bin_array.load()?.lb_pair == lb_pair.key()
+    )]
     pub bin_array: AccountLoader<'info, BinArray>,
 
     #[account(mut)]
@@ -30,201 +41,234 @@ pub struct Swap<'info> {
 
     #[account(
         mut,
-        constraint = reserve_x.mint == lb_pair.token_x_mint,
-        constraint = reserve_x.owner == lb_pair.key()
+        address = lb_pair.reserve_x_vault
     )]
     pub reserve_x: Account<'info, TokenAccount>,
 
     #[account(
         mut,
-        constraint = reserve_y.mint == lb_pair.token_y_mint,
-        constraint = reserve_y.owner == lb_pair.key()
+        address = lb_pair.reserve_y_vault
     )]
     pub reserve_y: Account<'info, TokenAccount>,
 
     pub token_program: Program<'info, Token>,
 }
 
-pub fn handler(
-    ctx: Context<Swap>,
-    amount_in: u64,
-    min_amount_out: u64,
-    swap_for_y: bool,
-) -> Result<()> {
-    let lb_pair = &mut ctx.accounts.lb_pair;
-    let mut bin_array = ctx.accounts.bin_array.load_mut()?;
+impl<'info> Swap<'info> {
+    /// Pulls `amount_in` from the user into the input reserve, pays `amount_out`
+    /// from the output reserve and books both on the pair.
+    pub fn settle(&mut self, swap_for_y: bool, amount_in: u64, amount_out: u64) -> Result<()> {
+        let (user_in, reserve_in, reserve_out, user_out) = if swap_for_y {
+            (
+                &self.user_x_token,
+                &self.reserve_x,
+                &self.reserve_y,
+                &self.user_y_token,
+            )
+        } else {
+            (
+                &self.user_y_token,
+                &self.reserve_y,
+                &self.reserve_x,
+                &self.user_x_token,
+            )
+        };
 
-    let mut amount_in_left = amount_in;
-    let mut amount_out = 0u64;
-    let mut fees_collected = 0u64;
-    let mut current_bin_id = lb_pair.active_bin_id;
+        let cpi_accounts = Transfer {
+            from: user_in.to_account_info(),
+            to: reserve_in.to_account_info(),
+            authority: self.user.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+        token::transfer(cpi_ctx, amount_in)?;
 
-    while amount_in_left > 0 {
-        let base_bin_id = (bin_array.index as i32) * 70;
-        let bin_index = (current_bin_id as i32 - base_bin_id) as usize;
+        let lb_pair = &mut self.lb_pair;
+        let seeds = &[
+            b"lb_pair",
+            lb_pair.token_x_mint.as_ref(),
+            lb_pair.token_y_mint.as_ref(),
+            &lb_pair.bin_step.to_le_bytes(),
+            &[lb_pair.bump],
+        ];
+        let signer = &[&seeds[..]];
 
-        require!(bin_index < 70, ErrorCode::BinOutOfRange);
-        let bin = &mut bin_array.bins[bin_index];
+        let cpi_accounts = Transfer {
+            from: reserve_out.to_account_info(),
+            to: user_out.to_account_info(),
+            authority: lb_pair.to_account_info(),
+        };
+        let cpi_ctx =
+            CpiContext::new_with_signer(self.token_program.to_account_info(), cpi_accounts, signer);
+        token::transfer(cpi_ctx, amount_out)?;
 
         if swap_for_y {
-            require!(bin.reserve_y > 0, ErrorCode::InsufficientLiquidity);
-
-            let max_amount_in = bin.reserve_x;
-            let amount_in_this_bin = amount_in_left.min(max_amount_in);
+            lb_pair.reserve_x = lb_pair
+                .reserve_x
+                .checked_add(amount_in)
+                .ok_or(ErrorCode::Overflow)?;
+            lb_pair.reserve_y = lb_pair
+                .reserve_y
+                .checked_sub(amount_out)
+                .ok_or(ErrorCode::Overflow)?;
+        } else {
+            lb_pair.reserve_y = lb_pair
+                .reserve_y
+                .checked_add(amount_in)
+                .ok_or(ErrorCode::Overflow)?;
+            lb_pair.reserve_x = lb_pair
+                .reserve_x
+                .checked_sub(amount_out)
+                .ok_or(ErrorCode::Overflow)?;
+        }
 
-            let fee = (amount_in_this_bin as u128 * lb_pair.base_free_rate as u128) / 10000;
-            let amount_in_after_fee = amount_in_this_bin - fee as u64;
+        Ok(())
+    }
+}
 
-            let amount_out_this_bin = (amount_in_after_fee as u128 * bin.reserve_y as u128)
-                / (bin.reserve_x as u128 + amount_in_after_fee as u128);
+/// Loads the extra bin arrays a swap may cross into, checking each belongs to `lb_pair`.
+pub fn load_bin_arrays<'info>(
+    lb_pair: &Pubkey,
+    remaining_accounts: &'info [AccountInfo<'info>],
+) -> Result<Vec<AccountLoader<'info, BinArray>>> {
+    let mut bin_arrays = Vec::with_capacity(remaining_accounts.len());
+    for account_info in remaining_accounts.iter() {
+        let bin_array = AccountLoader::<BinArray>::try_from(account_info)?;
+        require!(
+            bin_array.load()?.lb_pair == *lb_pair,
+            ErrorCode::InvalidBinArray
+        );
+        bin_arrays.push(bin_array);
+    }
+    Ok(bin_arrays)
+}
 
+/// Writes a quote's bin updates, protocol fee and new volatility state back
+/// to the accounts it was computed from. Moving tokens is left to the caller.
+pub fn apply_quote(
+    lb_pair: &mut LbPair,
+    bin_arrays: &mut [RefMut<BinArray>],
+    quote: &SwapQuote,
+    current_timestamp: u64,
+) -> Result<()> {
+    for step in quote.steps.iter() {
+        let bin = bin_arrays
+            .iter_mut()
+            .find_map(|bin_array| bin_array.bin_mut(step.bin_id))
+            .ok_or(ErrorCode::BinOutOfRange)?;
+
+        let amount_in_after_fee = step.amount_in - step.fee;
+        if quote.swap_for_y {
             bin.reserve_x = bin
                 .reserve_x
                 .checked_add(amount_in_after_fee)
                 .ok_or(ErrorCode::Overflow)?;
             bin.reserve_y = bin
                 .reserve_y
-                .checked_sub(amount_out_this_bin as u64)
+                .checked_sub(step.amount_out)
                 .ok_or(ErrorCode::InsufficientLiquidity)?;
-
-            amount_out += amount_out_this_bin as u64;
-            fees_collected += fee as u64;
-            amount_in_left -= amount_in_this_bin;
-
-            if bin.reserve_y == 0 {
-                current_bin_id -= 1;
-            }
         } else {
-            require!(bin.reserve_x > 0, ErrorCode::InsufficientLiquidity);
-
-            let max_amount_in = bin.reserve_y;
-            let amount_in_this_bin = amount_in_left.min(max_amount_in);
-
-            let fee = (amount_in_this_bin as u128 * lb_pair.base_free_rate as u128) / 10000;
-            let amount_in_after_fee = amount_in_this_bin - fee as u64;
-
-            let amount_out_this_bin = (amount_in_after_fee as u128 * bin.reserve_x as u128)
-                / (bin.reserve_y as u128 + amount_in_after_fee as u128);
-
             bin.reserve_y = bin
                 .reserve_y
                 .checked_add(amount_in_after_fee)
                 .ok_or(ErrorCode::Overflow)?;
             bin.reserve_x = bin
                 .reserve_x
-                .checked_sub(amount_out_this_bin as u64)
+                .checked_sub(step.amount_out)
                 .ok_or(ErrorCode::InsufficientLiquidity)?;
+        }
 
-            amount_out += amount_out_this_bin as u64;
-            fees_collected += fee as u64;
-            amount_in_left -= amount_in_this_bin;
+        bin.credit_fee(step.fee - step.protocol_fee, quote.swap_for_y)
+            .ok_or(ErrorCode::Overflow)?;
+    }
 
-            if bin.reserve_x == 0 {
-                current_bin_id += 1;
-            }
-        }
+    if quote.swap_for_y {
+        lb_pair.protocol_fee_x = lb_pair
+            .protocol_fee_x
+            .checked_add(quote.protocol_fee)
+            .ok_or(ErrorCode::Overflow)?;
+    } else {
+        lb_pair.protocol_fee_y = lb_pair
+            .protocol_fee_y
+            .checked_add(quote.protocol_fee)
+            .ok_or(ErrorCode::Overflow)?;
     }
 
-    require!(amount_out >= min_amount_out, ErrorCode::SlippageExceeded);
+    lb_pair.active_bin_id = quote.end_bin_id;
+    lb_pair.volatility_accumulator = quote.volatility_accumulator;
+    lb_pair.volatility_reference = quote.volatility_reference;
+    lb_pair.index_reference = quote.index_reference;
+    lb_pair.last_update_timestamp = current_timestamp;
 
-    if swap_for_y {
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.user_x_token.to_account_info(),
-            to: ctx.accounts.reserve_x.to_account_info(),
-            authority: ctx.accounts.user.to_account_info(),
-        };
-        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
-        token::transfer(cpi_ctx, amount_in)?;
+    Ok(())
+}
 
-        let seeds = &[
-            b"lb_pair",
-            lb_pair.token_x_mint.as_ref(),
-            lb_pair.token_y_mint.as_ref(),
-            &[lb_pair.bump],
-        ];
-        let signer = &[&seeds[..]];
+pub(crate) fn handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, Swap<'info>>,
+    amount_in: u64,
+    min_amount_out: u64,
+    swap_for_y: bool,
+) -> Result<()> {
+    let extra_bin_arrays = load_bin_arrays(&ctx.accounts.lb_pair.key(), ctx.remaining_accounts)?;
 
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.reserve_y.to_account_info(),
-            to: ctx.accounts.user_y_token.to_account_info(),
-            authority: lb_pair.to_account_info(),
-        };
-        let cpi_ctx = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            cpi_accounts,
-            signer,
-        );
-        token::transfer(cpi_ctx, amount_out)?;
-    } else {
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.user_y_token.to_account_info(),
-            to: ctx.accounts.reserve_y.to_account_info(),
-            authority: ctx.accounts.user.to_account_info(),
-        };
-        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
-        token::transfer(cpi_ctx, amount_in)?;
+    let mut bin_arrays = vec![ctx.accounts.bin_array.load_mut()?];
+    for bin_array in extra_bin_arrays.iter() {
+        bin_arrays.push(bin_array.load_mut()?);
+    }
 
-        let seeds = &[
-            b"lb_pair",
-            lb_pair.token_x_mint.as_ref(),
-            lb_pair.token_y_mint.as_ref(),
-            &[lb_pair.bump],
-        ];
-        let signer = &[&seeds[..]];
+    let current_timestamp = Clock::get()?.unix_timestamp as u64;
+    let quote = {
+        let snapshots: Vec<&BinArray> = bin_arrays.iter().map(|bin_array| &**bin_array).collect();
+        quote_exact_in(
+            &ctx.accounts.lb_pair,
+            &snapshots,
+            amount_in,
+            swap_for_y,
+            SwapLimit::default(),
+            current_timestamp,
+        )?
+    };
+
+    require!(
+        quote.amount_out >= min_amount_out,
+        ErrorCode::SlippageExceeded
+    );
 
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.reserve_x.to_account_info(),
-            to: ctx.accounts.user_x_token.to_account_info(),
-            authority: lb_pair.to_account_info(),
-        };
-        let cpi_ctx = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            cpi_accounts,
-            signer,
-        );
-        token::transfer(cpi_ctx, amount_out)?;
-    }
-    if swap_for_y {
-        lb_pair.reserve_x = lb_pair
-            .reserve_x
-            .checked_add(amount_in)
-            .ok_or(ErrorCode::Overflow)?;
-        lb_pair.reserve_y = lb_pair
-            .reserve_y
-            .checked_sub(amount_out)
-            .ok_or(ErrorCode::Overflow)?;
-    } else {
-        lb_pair.reserve_y = lb_pair
-            .reserve_y
-            .checked_add(amount_in)
-            .ok_or(ErrorCode::Overflow)?;
-        lb_pair.reserve_x = lb_pair
-            .reserve_x
-            .checked_sub(amount_out)
-            .ok_or(ErrorCode::Overflow)?;
-    }
+    apply_quote(
+        &mut ctx.accounts.lb_pair,
+        &mut bin_arrays,
+        &quote,
+        current_timestamp,
+    )?;
 
-    lb_pair.active_bin_id = current_bin_id;
+    drop(bin_arrays);
+    ctx.accounts
+        .settle(swap_for_y, quote.amount_in, quote.amount_out)?;
 
     msg!(
         "Swap complete: {} in, {} out, {} fees, active bin: {}",
-        amount_in,
-        amount_out,
-        fees_collected,
-        current_bin_id
+        quote.amount_in,
+        quote.amount_out,
+        quote.fee,
+        quote.end_bin_id
     );
 
     Ok(())
 }
 
 #[error_code]
-pub enum ErrorCode {
-    #[msg("Bin index out of range for this bin array")]
+pub(crate) enum ErrorCode {
+    #[msg("Bin index out of range for the provided bin arrays")]
     BinOutOfRange,
+    #[msg("Bin array does not belong to this pair")]
+    InvalidBinArray,
     #[msg("Insufficient liquidity in bin")]
     InsufficientLiquidity,
     #[msg("Arithmetic overflow")]
     Overflow,
     #[msg("Slippage exceeded")]
     SlippageExceeded,
+    #[msg("Swaps are disabled on this pair")]
+    SwapDisabled,
+    #[msg("Stop bin is on the wrong side of the active bin")]
+    InvalidStopBin,
 }
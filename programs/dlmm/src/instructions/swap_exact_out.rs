@@ -0,0 +1,59 @@
+use anchor_lang::prelude::*;
+
+use crate::instructions::swap::{apply_quote, load_bin_arrays, ErrorCode, Swap};
+use crate::quote::quote_exact_out;
+use crate::state::BinArray;
+
+/// Buys exactly `amount_out` of the output token, walking bins from the active
+/// one and charging whatever input (fee included) each bin's price requires.
+pub(crate) fn handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, Swap<'info>>,
+    amount_out: u64,
+    max_amount_in: u64,
+    swap_for_y: bool,
+) -> Result<()> {
+    let extra_bin_arrays = load_bin_arrays(&ctx.accounts.lb_pair.key(), ctx.remaining_accounts)?;
+
+    let mut bin_arrays = vec![ctx.accounts.bin_array.load_mut()?];
+    for bin_array in extra_bin_arrays.iter() {
+        bin_arrays.push(bin_array.load_mut()?);
+    }
+
+    let current_timestamp = Clock::get()?.unix_timestamp as u64;
+    let quote = {
+        let snapshots: Vec<&BinArray> = bin_arrays.iter().map(|bin_array| &**bin_array).collect();
+        quote_exact_out(
+            &ctx.accounts.lb_pair,
+            &snapshots,
+            amount_out,
+            swap_for_y,
+            current_timestamp,
+        )?
+    };
+
+    require!(
+        quote.amount_in <= max_amount_in,
+        ErrorCode::SlippageExceeded
+    );
+
+    apply_quote(
+        &mut ctx.accounts.lb_pair,
+        &mut bin_arrays,
+        &quote,
+        current_timestamp,
+    )?;
+
+    drop(bin_arrays);
+    ctx.accounts
+        .settle(swap_for_y, quote.amount_in, quote.amount_out)?;
+
+    msg!(
+        "Swap exact out complete: {} in, {} out, {} fees, active bin: {}",
+        quote.amount_in,
+        quote.amount_out,
+        quote.fee,
+        quote.end_bin_id
+    );
+
+    Ok(())
+}
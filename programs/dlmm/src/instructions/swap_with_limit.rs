@@ -0,0 +1,68 @@
+use anchor_lang::prelude::*;
+
+use crate::instructions::swap::{apply_quote, load_bin_arrays, ErrorCode, Swap};
+use crate::quote::{quote_exact_in, SwapLimit};
+use crate::state::BinArray;
+
+/// Exact-in swap with bounded price impact. Bins past `stop_bin_id` are never
+/// traded, and with `allow_partial_fill` running out of liquidity ends the
+/// swap instead of failing it. Only the consumed input is transferred.
+pub(crate) fn handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, Swap<'info>>,
+    amount_in: u64,
+    min_amount_out: u64,
+    swap_for_y: bool,
+    stop_bin_id: Option<i32>,
+    allow_partial_fill: bool,
+) -> Result<()> {
+    let extra_bin_arrays = load_bin_arrays(&ctx.accounts.lb_pair.key(), ctx.remaining_accounts)?;
+
+    let mut bin_arrays = vec![ctx.accounts.bin_array.load_mut()?];
+    for bin_array in extra_bin_arrays.iter() {
+        bin_arrays.push(bin_array.load_mut()?);
+    }
+
+    let current_timestamp = Clock::get()?.unix_timestamp as u64;
+    let limit = SwapLimit {
+        stop_bin_id,
+        allow_partial_fill,
+    };
+    let quote = {
+        let snapshots: Vec<&BinArray> = bin_arrays.iter().map(|bin_array| &**bin_array).collect();
+        quote_exact_in(
+            &ctx.accounts.lb_pair,
+            &snapshots,
+            amount_in,
+            swap_for_y,
+            limit,
+            current_timestamp,
+        )?
+    };
+
+    require!(
+        quote.amount_out >= min_amount_out,
+        ErrorCode::SlippageExceeded
+    );
+
+    apply_quote(
+        &mut ctx.accounts.lb_pair,
+        &mut bin_arrays,
+        &quote,
+        current_timestamp,
+    )?;
+
+    drop(bin_arrays);
+    ctx.accounts
+        .settle(swap_for_y, quote.amount_in, quote.amount_out)?;
+
+    msg!(
+        "Swap with limit complete: {} in, {} unspent, {} out, {} fees, active bin: {}",
+        quote.amount_in,
+        amount_in - quote.amount_in,
+        quote.amount_out,
+        quote.fee,
+        quote.end_bin_id
+    );
+
+    Ok(())
+}
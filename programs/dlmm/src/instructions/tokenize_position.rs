@@ -0,0 +1,97 @@
+use crate::state::Position;
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{self, Mint, MintTo, SetAuthority, Token, TokenAccount};
+
+#[derive(Accounts)]
+pub struct TokenizePosition<'info> {
+    #[account(
+        mut,
+        constraint = position.load()?.owner == owner.key() @ ErrorCode::Unauthorized,
+        constraint = !position.load()?.is_tokenized() @ ErrorCode::AlreadyTokenized
+    )]
+    pub position: AccountLoader<'info, Position>,
+
+    #[account(
+        init,
+        payer = owner,
+        seeds = [b"position_mint", position.key().as_ref()],
+        bump,
+        mint::decimals = 0,
+        mint::authority = position_mint,
+    )]
+    pub position_mint: Box<Account<'info, Mint>>,
+
+    #[account(
+        init,
+        payer = owner,
+        associated_token::mint = position_mint,
+        associated_token::authority = owner,
+    )]
+    pub owner_position_token: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Mints the position's single token to its owner and drops the mint
+/// authority. From then on the position is controlled by whoever holds the
+/// token, not by `owner`.
+pub(crate) fn handler(ctx: Context<TokenizePosition>) -> Result<()> {
+    let position_key = ctx.accounts.position.key();
+    let seeds = &[
+        b"position_mint",
+        position_key.as_ref(),
+        &[ctx.bumps.position_mint],
+    ];
+    let signer = &[&seeds[..]];
+
+    let cpi_accounts = MintTo {
+        mint: ctx.accounts.position_mint.to_account_info(),
+        to: ctx.accounts.owner_position_token.to_account_info(),
+        authority: ctx.accounts.position_mint.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        cpi_accounts,
+        signer,
+    );
+    token::mint_to(cpi_ctx, 1)?;
+
+    let cpi_accounts = SetAuthority {
+        current_authority: ctx.accounts.position_mint.to_account_info(),
+        account_or_mint: ctx.accounts.position_mint.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        cpi_accounts,
+        signer,
+    );
+    token::set_authority(
+        cpi_ctx,
+        token::spl_token::instruction::AuthorityType::MintTokens,
+        None,
+    )?;
+
+    let mut position = ctx.accounts.position.load_mut()?;
+    position.position_mint = ctx.accounts.position_mint.key();
+
+    msg!(
+        "Position {} tokenized as {}",
+        ctx.accounts.position.key(),
+        position.position_mint
+    );
+
+    Ok(())
+}
+
+#[error_code]
+pub(crate) enum ErrorCode {
+    #[msg("Signer does not own the position")]
+    Unauthorized,
+    #[msg("Position is already tokenized")]
+    AlreadyTokenized,
+}
@@ -0,0 +1,40 @@
+use crate::state::Position;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct TransferPositionOwner<'info> {
+    #[account(
+        mut,
+        constraint = position.load()?.owner == owner.key() @ ErrorCode::Unauthorized,
+        constraint = !position.load()?.is_tokenized() @ ErrorCode::PositionTokenized
+    )]
+    pub position: AccountLoader<'info, Position>,
+
+    pub owner: Signer<'info>,
+
+    /// CHECK: any account may own a position.
+    pub new_owner: UncheckedAccount<'info>,
+}
+
+/// Hands the position to `new_owner`. The position keeps its address; the new
+/// owner finds it by filtering positions on `owner`.
+pub(crate) fn handler(ctx: Context<TransferPositionOwner>) -> Result<()> {
+    let mut position = ctx.accounts.position.load_mut()?;
+    position.owner = ctx.accounts.new_owner.key();
+
+    msg!(
+        "Position {} transferred to {}",
+        ctx.accounts.position.key(),
+        position.owner
+    );
+
+    Ok(())
+}
+
+#[error_code]
+pub(crate) enum ErrorCode {
+    #[msg("Signer does not own the position")]
+    Unauthorized,
+    #[msg("Tokenized positions move with their position token")]
+    PositionTokenized,
+}
@@ -0,0 +1,110 @@
+use crate::state::{GlobalConfig, LbPair};
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+
+#[derive(Accounts)]
+pub struct WithdrawProtocolFee<'info> {
+    #[account(mut)]
+    pub lb_pair: Account<'info, LbPair>,
+
+    #[account(
+        mut,
+        address = lb_pair.reserve_x_vault
+    )]
+    pub reserve_x: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        address = lb_pair.reserve_y_vault
+    )]
+    pub reserve_y: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = treasury_token_x.mint == lb_pair.token_x_mint,
+    )]
+    pub treasury_token_x: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = treasury_token_y.mint == lb_pair.token_y_mint,
+    )]
+    pub treasury_token_y: Account<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        constraint = global_config.admin == authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+
+    pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+pub(crate) fn handler(ctx: Context<WithdrawProtocolFee>) -> Result<()> {
+    let lb_pair = &mut ctx.accounts.lb_pair;
+
+    let amount_x = lb_pair.protocol_fee_x;
+    let amount_y = lb_pair.protocol_fee_y;
+
+    let seeds = &[
+        b"lb_pair",
+        lb_pair.token_x_mint.as_ref(),
+        lb_pair.token_y_mint.as_ref(),
+        &lb_pair.bin_step.to_le_bytes(),
+        &[lb_pair.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    if amount_x > 0 {
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.reserve_x.to_account_info(),
+            to: ctx.accounts.treasury_token_x.to_account_info(),
+            authority: lb_pair.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        );
+        token::transfer(cpi_ctx, amount_x)?;
+    }
+
+    if amount_y > 0 {
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.reserve_y.to_account_info(),
+            to: ctx.accounts.treasury_token_y.to_account_info(),
+            authority: lb_pair.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        );
+        token::transfer(cpi_ctx, amount_y)?;
+    }
+
+    lb_pair.protocol_fee_x = 0;
+    lb_pair.protocol_fee_y = 0;
+    lb_pair.reserve_x = lb_pair
+        .reserve_x
+        .checked_sub(amount_x)
+        .ok_or(ErrorCode::Overflow)?;
+    lb_pair.reserve_y = lb_pair
+        .reserve_y
+        .checked_sub(amount_y)
+        .ok_or(ErrorCode::Overflow)?;
+
+    msg!("Protocol fees withdrawn: {} X, {} Y", amount_x, amount_y);
+
+    Ok(())
+}
+
+#[error_code]
+pub(crate) enum ErrorCode {
+    #[msg("Signer is not the admin")]
+    Unauthorized,
+    #[msg("Arithmetic overflow")]
+    Overflow,
+}
@@ -1,5 +1,7 @@
 use anchor_lang::prelude::*;
 pub mod instructions;
+pub mod math;
+pub mod quote;
 pub mod state;
 
 pub use instructions::*;
@@ -15,8 +17,24 @@ pub mod dlmm {
         Ok(())
     }
 
-    pub fn add_liquidity(
-        ctx: Context<AddLiquidity>,
+    pub fn initialize_position(
+        ctx: Context<InitializePosition>,
+        lower_bin_id: i32,
+        width: u32,
+    ) -> Result<()> {
+        instructions::initialize_position::handler(ctx, lower_bin_id, width)
+    }
+
+    pub fn transfer_position_owner(ctx: Context<TransferPositionOwner>) -> Result<()> {
+        instructions::transfer_position_owner::handler(ctx)
+    }
+
+    pub fn tokenize_position(ctx: Context<TokenizePosition>) -> Result<()> {
+        instructions::tokenize_position::handler(ctx)
+    }
+
+    pub fn add_liquidity<'info>(
+        ctx: Context<'_, '_, 'info, 'info, AddLiquidity<'info>>,
         amount_x: u64,
         amount_y: u64,
         bin_liquidity_dist: Vec<BinLiquidityDistribution>,
@@ -24,15 +42,53 @@ pub mod dlmm {
         instructions::add_liquidity::handler(ctx, amount_x, amount_y, bin_liquidity_dist)
     }
 
-    pub fn remove_liquidity(
-        ctx: Context<RemoveLiquidity>,
+    pub fn add_liquidity_by_strategy<'info>(
+        ctx: Context<'_, '_, 'info, 'info, AddLiquidity<'info>>,
+        amount_x: u64,
+        amount_y: u64,
+        strategy: StrategyParameters,
+    ) -> Result<()> {
+        instructions::add_liquidity_by_strategy::handler(ctx, amount_x, amount_y, strategy)
+    }
+
+    pub fn remove_liquidity<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RemoveLiquidity<'info>>,
         bin_liquidity_removal: Vec<BinLiquidityReduction>,
     ) -> Result<()> {
         instructions::remove_liquidity::handler(ctx, bin_liquidity_removal)
     }
 
-    pub fn swap(
-        ctx: Context<Swap>,
+    pub fn remove_liquidity_by_range<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RemoveLiquidity<'info>>,
+        from_bin_id: i32,
+        to_bin_id: i32,
+        bps: u16,
+    ) -> Result<()> {
+        instructions::remove_liquidity_by_range::handler(ctx, from_bin_id, to_bin_id, bps)
+    }
+
+    pub fn rebalance_position<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RemoveLiquidity<'info>>,
+        bin_liquidity_removal: Vec<BinLiquidityReduction>,
+        new_lower_bin_id: Option<i32>,
+        swap: Option<RebalanceSwap>,
+        bin_liquidity_dist: Vec<BinLiquidityDistribution>,
+    ) -> Result<()> {
+        instructions::rebalance_position::handler(
+            ctx,
+            bin_liquidity_removal,
+            new_lower_bin_id,
+            swap,
+            bin_liquidity_dist,
+        )
+    }
+
+    pub fn close_position(ctx: Context<ClosePosition>) -> Result<()> {
+        instructions::close_position::handler(ctx)
+    }
+
+    pub fn swap<'info>(
+        ctx: Context<'_, '_, 'info, 'info, Swap<'info>>,
         amount_in: u64,
         min_amount_out: u64,
         swap_for_y: bool,
@@ -40,12 +96,70 @@ pub mod dlmm {
         instructions::swap::handler(ctx, amount_in, min_amount_out, swap_for_y)
     }
 
-    pub fn initialize_lb_pair(ctx: Context<InitializeLbPair>, bin_step: u16) -> Result<()> {
-        instructions::initialize_lbpair::handler(ctx, bin_step)
+    pub fn swap_exact_out<'info>(
+        ctx: Context<'_, '_, 'info, 'info, Swap<'info>>,
+        amount_out: u64,
+        max_amount_in: u64,
+        swap_for_y: bool,
+    ) -> Result<()> {
+        instructions::swap_exact_out::handler(ctx, amount_out, max_amount_in, swap_for_y)
+    }
+
+    pub fn swap_with_limit<'info>(
+        ctx: Context<'_, '_, 'info, 'info, Swap<'info>>,
+        amount_in: u64,
+        min_amount_out: u64,
+        swap_for_y: bool,
+        stop_bin_id: Option<i32>,
+        allow_partial_fill: bool,
+    ) -> Result<()> {
+        instructions::swap_with_limit::handler(
+            ctx,
+            amount_in,
+            min_amount_out,
+            swap_for_y,
+            stop_bin_id,
+            allow_partial_fill,
+        )
+    }
+
+    pub fn claim_fee<'info>(ctx: Context<'_, '_, 'info, 'info, ClaimFee<'info>>) -> Result<()> {
+        instructions::claim_fee::handler(ctx)
+    }
+
+    pub fn withdraw_protocol_fee(ctx: Context<WithdrawProtocolFee>) -> Result<()> {
+        instructions::withdraw_protocol_fee::handler(ctx)
+    }
+
+    pub fn initialize_global_config(ctx: Context<InitializeGlobalConfig>) -> Result<()> {
+        instructions::initialize_global_config::handler(ctx)
+    }
+
+    pub fn set_admin(ctx: Context<SetAdmin>) -> Result<()> {
+        instructions::set_admin::handler(ctx)
+    }
+
+    pub fn set_guardian(ctx: Context<SetGuardian>) -> Result<()> {
+        instructions::set_guardian::handler(ctx)
+    }
+
+    pub fn set_pair_status(ctx: Context<SetPairStatus>, status: u8) -> Result<()> {
+        instructions::set_pair_status::handler(ctx, status)
+    }
+
+    pub fn initialize_preset_parameter(
+        ctx: Context<InitializePresetParameter>,
+        args: InitPresetParameterArgs,
+    ) -> Result<()> {
+        instructions::initialize_preset_parameter::handler(ctx, args)
+    }
+
+    pub fn initialize_lb_pair(ctx: Context<InitializeLbPair>, active_id: i32) -> Result<()> {
+        instructions::initialize_lbpair::handler(ctx, active_id)
     }
 
     pub fn initialize_bin_array(ctx: Context<InitializeBinArray>, index: i32) -> Result<()> {
-        instructions::initialize_bin::handler(ctx, index as i32)
+        instructions::initialize_bin::handler(ctx, index)
     }
 }
 
@@ -0,0 +1,323 @@
+//! Fixed-point math for bin pricing.
+//!
+//! Prices are unsigned Q64.64 numbers and quote token Y in units of token X,
+//! so a bin with price `p` buys one Y for `p` X. Bin 0 is price 1.0 and every
+//! step up multiplies the price by `1 + bin_step / 10000`.
+
+pub const BASIS_POINT_MAX: u64 = 10000;
+
+/// Swap fee rates are expressed in parts per `FEE_PRECISION`.
+pub const FEE_PRECISION: u64 = 1_000_000_000;
+pub const MAX_FEE_RATE: u64 = 100_000_000;
+/// Brings `variable_fee_control * (volatility_accumulator * bin_step)^2` down to `FEE_PRECISION`.
+pub const VARIABLE_FEE_SCALE: u128 = 100_000_000_000;
+
+pub const SCALE_OFFSET: u32 = 64;
+pub const ONE: u128 = 1 << SCALE_OFFSET;
+
+/// Largest bin id at `bin_step = 1` whose price still fits in Q64.64.
+pub const MAX_BIN_ID: i32 = 443636;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    Up,
+    Down,
+}
+
+/// Largest usable bin id for `bin_step`. Wider steps reach the same price
+/// range in proportionally fewer bins.
+pub fn max_bin_id(bin_step: u16) -> i32 {
+    MAX_BIN_ID / bin_step.max(1) as i32
+}
+
+/// Smallest usable bin id for `bin_step`; prices below 1.0 mirror those above.
+pub fn min_bin_id(bin_step: u16) -> i32 {
+    -max_bin_id(bin_step)
+}
+
+pub fn is_bin_id_in_range(bin_id: i32, bin_step: u16) -> bool {
+    (min_bin_id(bin_step)..=max_bin_id(bin_step)).contains(&bin_id)
+}
+
+/// `(1 + bin_step / 10000) ^ bin_id` as a Q64.64 number.
+pub fn price_from_bin_id(bin_id: i32, bin_step: u16) -> Option<u128> {
+    let base = ONE.checked_add((bin_step as u128) * ONE / BASIS_POINT_MAX as u128)?;
+    pow(base, bin_id)
+}
+
+/// Bin whose price is nearest to the Q64.64 `price`, or `None` if the price is
+/// outside the range `bin_step` can reach.
+pub fn bin_id_from_price(price: u128, bin_step: u16) -> Option<i32> {
+    let (mut low, mut high) = (min_bin_id(bin_step), max_bin_id(bin_step));
+    if price < price_from_bin_id(low, bin_step)? || price > price_from_bin_id(high, bin_step)? {
+        return None;
+    }
+
+    // Largest bin priced at or below `price`.
+    while low < high {
+        let mid = low + (high - low + 1) / 2;
+        if price_from_bin_id(mid, bin_step)? <= price {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+
+    if low == max_bin_id(bin_step) {
+        return Some(low);
+    }
+    let below = price - price_from_bin_id(low, bin_step)?;
+    let above = price_from_bin_id(low + 1, bin_step)? - price;
+    Some(if above < below { low + 1 } else { low })
+}
+
+/// `variable_fee_control * (volatility_accumulator * bin_step)^2`, scaled to `FEE_PRECISION`.
+pub fn variable_fee_rate(
+    volatility_accumulator: u64,
+    bin_step: u16,
+    variable_fee_control: u32,
+) -> Option<u64> {
+    let square_vfa_bin = (volatility_accumulator as u128)
+        .checked_mul(bin_step as u128)?
+        .checked_pow(2)?;
+    let variable_fee = square_vfa_bin.checked_mul(variable_fee_control as u128)?;
+    u64::try_from(variable_fee.div_ceil(VARIABLE_FEE_SCALE)).ok()
+}
+
+/// Raises a Q64.64 number to a signed integer power.
+pub fn pow(base: u128, exp: i32) -> Option<u128> {
+    let invert = exp < 0;
+    let mut exp = exp.unsigned_abs();
+    let mut base = base;
+    let mut result = ONE;
+
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_div(result, base, ONE, Rounding::Down)?;
+        }
+        exp >>= 1;
+        if exp > 0 {
+            base = mul_div(base, base, ONE, Rounding::Down)?;
+        }
+    }
+
+    if invert {
+        mul_div(ONE, ONE, result, Rounding::Down)
+    } else {
+        Some(result)
+    }
+}
+
+/// Amount of the output token a bin pays for `amount_in`, rounded down.
+pub fn get_amount_out(amount_in: u64, price: u128, swap_for_y: bool) -> Option<u64> {
+    let amount_out = if swap_for_y {
+        mul_div(amount_in as u128, ONE, price, Rounding::Down)?
+    } else {
+        mul_div(amount_in as u128, price, ONE, Rounding::Down)?
+    };
+    u64::try_from(amount_out).ok()
+}
+
+/// Amount of the input token a bin needs to pay out `amount_out`, rounded up.
+pub fn get_amount_in(amount_out: u64, price: u128, swap_for_y: bool) -> Option<u64> {
+    let amount_in = if swap_for_y {
+        mul_div(amount_out as u128, price, ONE, Rounding::Up)?
+    } else {
+        mul_div(amount_out as u128, ONE, price, Rounding::Up)?
+    };
+    u64::try_from(amount_in).ok()
+}
+
+/// Value of `amount_x` and `amount_y` in units of X at `price`, rounded down.
+/// This is the liquidity `L = x + price * y` that bin shares are minted against.
+pub fn get_liquidity(amount_x: u64, amount_y: u64, price: u128) -> Option<u128> {
+    let value_y = mul_div(amount_y as u128, price, ONE, Rounding::Down)?;
+    (amount_x as u128).checked_add(value_y)
+}
+
+/// Fee charged on an input amount that already includes the fee, rounded up.
+pub fn fee_from_amount(amount: u64, fee_rate: u64) -> Option<u64> {
+    let fee = mul_div(
+        amount as u128,
+        fee_rate as u128,
+        FEE_PRECISION as u128,
+        Rounding::Up,
+    )?;
+    u64::try_from(fee).ok()
+}
+
+/// Fee to add on top of `amount` so that `amount` is left once it is taken, rounded up.
+pub fn fee_for_amount_after_fee(amount: u64, fee_rate: u64) -> Option<u64> {
+    let denominator = FEE_PRECISION.checked_sub(fee_rate)?;
+    let fee = mul_div(
+        amount as u128,
+        fee_rate as u128,
+        denominator as u128,
+        Rounding::Up,
+    )?;
+    u64::try_from(fee).ok()
+}
+
+/// `x * y / denominator` with a 256-bit intermediate product.
+pub fn mul_div(x: u128, y: u128, denominator: u128, rounding: Rounding) -> Option<u128> {
+    if denominator == 0 {
+        return None;
+    }
+
+    let (hi, lo) = full_mul(x, y);
+    let (quotient, remainder) = if hi == 0 {
+        (lo / denominator, lo % denominator)
+    } else {
+        div_rem_wide(hi, lo, denominator)?
+    };
+
+    if rounding == Rounding::Up && remainder > 0 {
+        quotient.checked_add(1)
+    } else {
+        Some(quotient)
+    }
+}
+
+fn full_mul(x: u128, y: u128) -> (u128, u128) {
+    const MASK: u128 = u64::MAX as u128;
+
+    let (x1, x0) = (x >> 64, x & MASK);
+    let (y1, y0) = (y >> 64, y & MASK);
+
+    let p00 = x0 * y0;
+    let p01 = x0 * y1;
+    let p10 = x1 * y0;
+    let p11 = x1 * y1;
+
+    let mid = (p00 >> 64) + (p01 & MASK) + (p10 & MASK);
+    let lo = (p00 & MASK) | (mid << 64);
+    let hi = p11 + (p01 >> 64) + (p10 >> 64) + (mid >> 64);
+    (hi, lo)
+}
+
+fn div_rem_wide(hi: u128, lo: u128, denominator: u128) -> Option<(u128, u128)> {
+    // The quotient only fits in 128 bits when the high word is below the divisor.
+    if hi >= denominator {
+        return None;
+    }
+
+    let mut remainder = hi;
+    let mut quotient = 0u128;
+    for i in (0..128).rev() {
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((lo >> i) & 1);
+        quotient <<= 1;
+        if carry == 1 || remainder >= denominator {
+            remainder = remainder.wrapping_sub(denominator);
+            quotient |= 1;
+        }
+    }
+    Some((quotient, remainder))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_f64(price: u128) -> f64 {
+        price as f64 / ONE as f64
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            ((actual - expected) / expected).abs() < 1e-9,
+            "{actual} != {expected}"
+        );
+    }
+
+    #[test]
+    fn price_one_bin_either_side_of_zero() {
+        assert_eq!(price_from_bin_id(0, 100), Some(ONE));
+        assert_close(to_f64(price_from_bin_id(1, 100).unwrap()), 1.01);
+        assert_close(to_f64(price_from_bin_id(-1, 100).unwrap()), 1.0 / 1.01);
+        assert_close(to_f64(price_from_bin_id(-1, 100).unwrap()), 0.990099009901);
+    }
+
+    #[test]
+    fn price_at_bin_id_bounds() {
+        for bin_step in [1, 10, 100, 10000] {
+            let max = price_from_bin_id(max_bin_id(bin_step), bin_step).unwrap();
+            let min = price_from_bin_id(min_bin_id(bin_step), bin_step).unwrap();
+            assert!(max > ONE);
+            assert!(min > 0 && min < ONE);
+            assert!(!is_bin_id_in_range(max_bin_id(bin_step) + 1, bin_step));
+            assert!(!is_bin_id_in_range(min_bin_id(bin_step) - 1, bin_step));
+        }
+        assert_close(
+            to_f64(price_from_bin_id(max_bin_id(100), 100).unwrap()),
+            1.01f64.powi(max_bin_id(100)),
+        );
+    }
+
+    #[test]
+    fn variable_fee_rate_scales_with_volatility() {
+        assert_eq!(variable_fee_rate(0, 100, 7500), Some(0));
+        // 7500 * (150_000 * 100)^2 / 1e11 = 16_875_000, i.e. 1.6875%.
+        assert_eq!(variable_fee_rate(150_000, 100, 7500), Some(16_875_000));
+        assert_eq!(variable_fee_rate(1, 1, 1), Some(1));
+        assert_eq!(variable_fee_rate(u64::MAX, 10000, u32::MAX), None);
+    }
+
+    #[test]
+    fn mul_div_overflow_returns_none() {
+        assert_eq!(mul_div(u128::MAX, u128::MAX, 1, Rounding::Down), None);
+        assert_eq!(mul_div(u128::MAX, 2, 1, Rounding::Down), None);
+        assert_eq!(mul_div(1, 1, 0, Rounding::Down), None);
+        assert_eq!(mul_div(u128::MAX, 1, 1, Rounding::Up), Some(u128::MAX));
+    }
+
+    #[test]
+    fn mul_div_rounds_and_handles_wide_products() {
+        assert_eq!(mul_div(10, 1, 3, Rounding::Down), Some(3));
+        assert_eq!(mul_div(10, 1, 3, Rounding::Up), Some(4));
+        assert_eq!(mul_div(9, 1, 3, Rounding::Up), Some(3));
+        // 2^127 * 6 / 4 needs the 256-bit path.
+        assert_eq!(mul_div(1 << 127, 6, 4, Rounding::Down), Some(3 << 126));
+        assert_eq!(
+            mul_div(u128::MAX, u128::MAX, u128::MAX, Rounding::Down),
+            Some(u128::MAX)
+        );
+    }
+
+    #[test]
+    fn bin_id_from_price_round_trips() {
+        for bin_step in [1, 25, 100] {
+            for bin_id in [-500, -1, 0, 1, 500, max_bin_id(bin_step)] {
+                let price = price_from_bin_id(bin_id, bin_step).unwrap();
+                assert_eq!(bin_id_from_price(price, bin_step), Some(bin_id));
+            }
+        }
+    }
+
+    #[test]
+    fn bin_id_from_price_at_min_bin() {
+        // Near the bottom prices are a handful of Q64.64 units, so neighbouring
+        // bins can share a price and the highest of them is returned.
+        let price = price_from_bin_id(min_bin_id(100), 100).unwrap();
+        let bin_id = bin_id_from_price(price, 100).unwrap();
+        assert!(bin_id >= min_bin_id(100));
+        assert_eq!(price_from_bin_id(bin_id, 100), Some(price));
+    }
+
+    #[test]
+    fn bin_id_from_price_picks_nearest_bin() {
+        let low = price_from_bin_id(10, 100).unwrap();
+        let high = price_from_bin_id(11, 100).unwrap();
+        let gap = high - low;
+        assert_eq!(bin_id_from_price(low + gap / 3, 100), Some(10));
+        assert_eq!(bin_id_from_price(high - gap / 3, 100), Some(11));
+        assert_eq!(bin_id_from_price(low - 1, 100), Some(10));
+    }
+
+    #[test]
+    fn bin_id_from_price_rejects_unreachable_prices() {
+        let max = price_from_bin_id(max_bin_id(100), 100).unwrap();
+        assert_eq!(bin_id_from_price(max + 1, 100), None);
+        assert_eq!(bin_id_from_price(0, 100), None);
+    }
+}
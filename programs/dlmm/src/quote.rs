@@ -0,0 +1,569 @@
+//! Side-effect-free swap simulation.
+//!
+//! The swap instructions quote against the loaded bin arrays and then apply
+//! the result, so an off-chain caller holding the same `LbPair` and
+//! `BinArray` snapshots gets exactly what the program would do. Failures use
+//! the swap instructions' `ErrorCode`, so every error code has one meaning.
+
+use anchor_lang::prelude::*;
+
+use crate::instructions::swap::ErrorCode;
+use crate::math::{
+    fee_for_amount_after_fee, fee_from_amount, get_amount_in, get_amount_out, is_bin_id_in_range,
+    mul_div, price_from_bin_id, Rounding, BASIS_POINT_MAX,
+};
+use crate::state::{BinArray, LbPair};
+
+/// Optional bounds on an exact-in swap. The default is a plain swap that must
+/// fill completely.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SwapLimit {
+    /// Last bin the swap may trade in.
+    pub stop_bin_id: Option<i32>,
+    /// End the swap, rather than fail it, when liquidity runs out.
+    pub allow_partial_fill: bool,
+}
+
+/// What a swap did inside a single bin.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BinSwap {
+    pub bin_id: i32,
+    /// Input paid into the bin, fee included.
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee: u64,
+    /// Part of `fee` kept by the protocol rather than credited to LPs.
+    pub protocol_fee: u64,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SwapQuote {
+    pub swap_for_y: bool,
+    /// Input consumed, fee included.
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee: u64,
+    pub protocol_fee: u64,
+    pub start_bin_id: i32,
+    /// Active bin once the swap is applied.
+    pub end_bin_id: i32,
+    pub bins_crossed: u32,
+    /// Move from the start to the end bin price, in basis points of the start price.
+    pub price_impact_bps: u64,
+    pub volatility_accumulator: u64,
+    pub volatility_reference: u64,
+    pub index_reference: i32,
+    pub steps: Vec<BinSwap>,
+}
+
+/// Quotes a swap spending up to `amount_in`.
+pub fn quote_exact_in(
+    lb_pair: &LbPair,
+    bin_arrays: &[&BinArray],
+    amount_in: u64,
+    swap_for_y: bool,
+    limit: SwapLimit,
+    current_timestamp: u64,
+) -> Result<SwapQuote> {
+    quote_swap(
+        lb_pair,
+        bin_arrays,
+        amount_in,
+        true,
+        swap_for_y,
+        limit,
+        current_timestamp,
+    )
+}
+
+/// Quotes a swap delivering exactly `amount_out`.
+pub fn quote_exact_out(
+    lb_pair: &LbPair,
+    bin_arrays: &[&BinArray],
+    amount_out: u64,
+    swap_for_y: bool,
+    current_timestamp: u64,
+) -> Result<SwapQuote> {
+    quote_swap(
+        lb_pair,
+        bin_arrays,
+        amount_out,
+        false,
+        swap_for_y,
+        SwapLimit::default(),
+        current_timestamp,
+    )
+}
+
+fn quote_swap(
+    lb_pair: &LbPair,
+    bin_arrays: &[&BinArray],
+    amount: u64,
+    exact_in: bool,
+    swap_for_y: bool,
+    limit: SwapLimit,
+    current_timestamp: u64,
+) -> Result<SwapQuote> {
+    let mut lb_pair = lb_pair.clone();
+    lb_pair
+        .update_references(current_timestamp)
+        .ok_or(ErrorCode::Overflow)?;
+
+    if let Some(stop_bin_id) = limit.stop_bin_id {
+        let valid = if swap_for_y {
+            stop_bin_id >= lb_pair.active_bin_id
+        } else {
+            stop_bin_id <= lb_pair.active_bin_id
+        };
+        require!(valid, ErrorCode::InvalidStopBin);
+    }
+
+    let mut quote = SwapQuote {
+        swap_for_y,
+        start_bin_id: lb_pair.active_bin_id,
+        ..Default::default()
+    };
+    let mut amount_left = amount;
+    let mut current_bin_id = lb_pair.active_bin_id;
+
+    while amount_left > 0 {
+        let past_stop_bin = match limit.stop_bin_id {
+            Some(stop_bin_id) if swap_for_y => current_bin_id > stop_bin_id,
+            Some(stop_bin_id) => current_bin_id < stop_bin_id,
+            None => false,
+        };
+        if past_stop_bin {
+            break;
+        }
+
+        let Some(bin) = bin_arrays
+            .iter()
+            .find_map(|bin_array| bin_array.bin(current_bin_id))
+        else {
+            require!(limit.allow_partial_fill, ErrorCode::BinOutOfRange);
+            break;
+        };
+
+        let reserve_out = if swap_for_y {
+            bin.reserve_y
+        } else {
+            bin.reserve_x
+        };
+
+        // A bin with nothing left to sell is crossed without trading.
+        let mut drained = reserve_out == 0;
+        if !drained {
+            lb_pair
+                .update_volatility_accumulator(current_bin_id)
+                .ok_or(ErrorCode::Overflow)?;
+            let fee_rate = lb_pair.total_fee_rate().ok_or(ErrorCode::Overflow)?;
+            let price =
+                price_from_bin_id(current_bin_id, lb_pair.bin_step).ok_or(ErrorCode::Overflow)?;
+
+            let (amount_in, amount_out, fee) = if exact_in {
+                swap_exact_in_bin(reserve_out, price, fee_rate, amount_left, swap_for_y)
+            } else {
+                swap_exact_out_bin(reserve_out, price, fee_rate, amount_left, swap_for_y)
+            }
+            .ok_or(ErrorCode::Overflow)?;
+            let protocol_fee = lb_pair.protocol_fee(fee).ok_or(ErrorCode::Overflow)?;
+
+            amount_left -= if exact_in { amount_in } else { amount_out };
+            quote.amount_in = quote
+                .amount_in
+                .checked_add(amount_in)
+                .ok_or(ErrorCode::Overflow)?;
+            quote.amount_out += amount_out;
+            quote.fee += fee;
+            quote.protocol_fee += protocol_fee;
+            quote.steps.push(BinSwap {
+                bin_id: current_bin_id,
+                amount_in,
+                amount_out,
+                fee,
+                protocol_fee,
+            });
+            drained = amount_out == reserve_out;
+        }
+
+        // Buying Y pushes the price of Y up, so a drained bin hands over to the one above it.
+        if drained {
+            let next_bin_id = if swap_for_y {
+                current_bin_id + 1
+            } else {
+                current_bin_id - 1
+            };
+            if is_bin_id_in_range(next_bin_id, lb_pair.bin_step) {
+                current_bin_id = next_bin_id;
+            } else {
+                require!(
+                    amount_left == 0 || limit.allow_partial_fill,
+                    ErrorCode::BinOutOfRange
+                );
+                break;
+            }
+        }
+    }
+
+    quote.end_bin_id = current_bin_id;
+    quote.bins_crossed = quote.start_bin_id.abs_diff(quote.end_bin_id);
+    quote.price_impact_bps =
+        price_impact_bps(quote.start_bin_id, quote.end_bin_id, lb_pair.bin_step)
+            .ok_or(ErrorCode::Overflow)?;
+    quote.volatility_accumulator = lb_pair.volatility_accumulator;
+    quote.volatility_reference = lb_pair.volatility_reference;
+    quote.index_reference = lb_pair.index_reference;
+
+    Ok(quote)
+}
+
+/// Spends up to `amount_in` in one bin. Returns `(amount_in, amount_out, fee)`
+/// where `amount_in` includes the fee.
+fn swap_exact_in_bin(
+    reserve_out: u64,
+    price: u128,
+    fee_rate: u64,
+    amount_in: u64,
+    swap_for_y: bool,
+) -> Option<(u64, u64, u64)> {
+    // Input (fee included) that drains the whole output reserve at this bin's price.
+    let max_amount_in_after_fee = get_amount_in(reserve_out, price, swap_for_y)?;
+    let max_fee = fee_for_amount_after_fee(max_amount_in_after_fee, fee_rate)?;
+    let max_amount_in = max_amount_in_after_fee.checked_add(max_fee)?;
+
+    if amount_in >= max_amount_in {
+        return Some((max_amount_in, reserve_out, max_fee));
+    }
+
+    let fee = fee_from_amount(amount_in, fee_rate)?;
+    let amount_out = get_amount_out(amount_in - fee, price, swap_for_y)?.min(reserve_out);
+    Some((amount_in, amount_out, fee))
+}
+
+/// Buys up to `amount_out` from one bin. Both the input and the fee on top of
+/// it round up, in the pool's favour.
+fn swap_exact_out_bin(
+    reserve_out: u64,
+    price: u128,
+    fee_rate: u64,
+    amount_out: u64,
+    swap_for_y: bool,
+) -> Option<(u64, u64, u64)> {
+    let amount_out = amount_out.min(reserve_out);
+    let amount_in_after_fee = get_amount_in(amount_out, price, swap_for_y)?;
+    let fee = fee_for_amount_after_fee(amount_in_after_fee, fee_rate)?;
+    Some((amount_in_after_fee.checked_add(fee)?, amount_out, fee))
+}
+
+fn price_impact_bps(start_bin_id: i32, end_bin_id: i32, bin_step: u16) -> Option<u64> {
+    let start_price = price_from_bin_id(start_bin_id, bin_step)?;
+    let end_price = price_from_bin_id(end_bin_id, bin_step)?;
+    let impact = mul_div(
+        start_price.abs_diff(end_price),
+        BASIS_POINT_MAX as u128,
+        start_price,
+        Rounding::Down,
+    )?;
+    u64::try_from(impact).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytemuck::Zeroable;
+
+    const BIN_STEP: u16 = 100;
+
+    /// A fee-free pair at `active_bin_id` unless a test sets the fee fields.
+    fn lb_pair(active_bin_id: i32) -> LbPair {
+        LbPair {
+            token_x_mint: Pubkey::default(),
+            token_y_mint: Pubkey::default(),
+            reserve_x: 0,
+            reserve_y: 0,
+            bin_step: BIN_STEP,
+            active_bin_id,
+            base_free_rate: 0,
+            protocol_fee_rate: 0,
+            volatility_accumulator: 0,
+            last_update_timestamp: 0,
+            bump: 0,
+            volatility_reference: 0,
+            index_reference: active_bin_id,
+            filter_period: 30,
+            decay_period: 600,
+            reduction_factor: 5000,
+            variable_fee_control: 0,
+            max_volatility_accumulator: 150_000,
+            protocol_fee_x: 0,
+            protocol_fee_y: 0,
+            reserve_x_vault: Pubkey::default(),
+            reserve_y_vault: Pubkey::default(),
+            status: 0,
+        }
+    }
+
+    /// Bin array 0 holding `(bin_id, reserve_x, reserve_y)` for each entry.
+    fn bin_array(bins: &[(i32, u64, u64)]) -> BinArray {
+        let mut bin_array = BinArray::zeroed();
+        for (bin_id, reserve_x, reserve_y) in bins {
+            let bin = bin_array.bin_mut(*bin_id).unwrap();
+            bin.bin_id = *bin_id;
+            bin.reserve_x = *reserve_x;
+            bin.reserve_y = *reserve_y;
+        }
+        bin_array
+    }
+
+    #[test]
+    fn swap_skips_bin_drained_in_swap_direction() {
+        // An X for Y swap drained bin 0's Y and left the active bin at 1,
+        // which holds only Y; selling Y must walk back down to bin 0.
+        let lb_pair = lb_pair(1);
+        let bin_array = bin_array(&[(0, 2000, 0), (1, 0, 1000)]);
+
+        let quote =
+            quote_exact_in(&lb_pair, &[&bin_array], 10, false, SwapLimit::default(), 0).unwrap();
+        assert_eq!(quote.amount_out, 10);
+        assert_eq!(quote.end_bin_id, 0);
+        assert_eq!(quote.steps.len(), 1);
+        assert_eq!(quote.steps[0].bin_id, 0);
+
+        let quote = quote_exact_out(&lb_pair, &[&bin_array], 10, false, 0).unwrap();
+        assert_eq!(quote.amount_out, 10);
+        assert_eq!(quote.steps[0].bin_id, 0);
+    }
+
+    #[test]
+    fn swap_skips_empty_bins_upwards() {
+        // Active bin 0 holds only X; buying Y skips it and the empty bin 1.
+        let lb_pair = lb_pair(0);
+        let bin_array = bin_array(&[(0, 1000, 0), (2, 0, 1000)]);
+
+        let quote =
+            quote_exact_in(&lb_pair, &[&bin_array], 100, true, SwapLimit::default(), 0).unwrap();
+        assert_eq!(quote.end_bin_id, 2);
+        assert_eq!(quote.steps.len(), 1);
+        assert_eq!(quote.steps[0].bin_id, 2);
+
+        let quote = quote_exact_out(&lb_pair, &[&bin_array], 100, true, 0).unwrap();
+        assert_eq!(quote.amount_out, 100);
+        assert_eq!(quote.steps[0].bin_id, 2);
+    }
+
+    #[test]
+    fn swap_fails_when_bin_arrays_run_out() {
+        let lb_pair = lb_pair(0);
+        let bin_array = bin_array(&[(0, 1000, 0)]);
+
+        let result = quote_exact_in(&lb_pair, &[&bin_array], 100, true, SwapLimit::default(), 0);
+        assert_eq!(result.unwrap_err(), ErrorCode::BinOutOfRange.into());
+
+        let limit = SwapLimit {
+            stop_bin_id: None,
+            allow_partial_fill: true,
+        };
+        let quote = quote_exact_in(&lb_pair, &[&bin_array], 100, true, limit, 0).unwrap();
+        assert_eq!(quote.amount_in, 0);
+        assert_eq!(quote.end_bin_id, 70);
+    }
+
+    #[test]
+    fn exact_in_crosses_bins_with_fees() {
+        // 0.1% base fee, a fifth of it to the protocol.
+        let mut lb_pair = lb_pair(0);
+        lb_pair.base_free_rate = 10;
+        lb_pair.protocol_fee_rate = 2000;
+        let bin_array = bin_array(&[(0, 0, 1_000_000), (1, 0, 1_000_000)]);
+
+        let quote = quote_exact_in(
+            &lb_pair,
+            &[&bin_array],
+            1_500_000,
+            true,
+            SwapLimit::default(),
+            0,
+        )
+        .unwrap();
+
+        // Bin 0 at price 1 is drained for 1_000_000 plus a 1002 fee rounded up.
+        assert_eq!(
+            quote.steps[0],
+            BinSwap {
+                bin_id: 0,
+                amount_in: 1_001_002,
+                amount_out: 1_000_000,
+                fee: 1002,
+                protocol_fee: 200,
+            }
+        );
+        // The remaining 498_998 buys at 1.01 after a 499 fee.
+        assert_eq!(
+            quote.steps[1],
+            BinSwap {
+                bin_id: 1,
+                amount_in: 498_998,
+                amount_out: 493_563,
+                fee: 499,
+                protocol_fee: 99,
+            }
+        );
+        assert_eq!(quote.amount_in, 1_500_000);
+        assert_eq!(quote.amount_out, 1_493_563);
+        assert_eq!(quote.fee, 1501);
+        assert_eq!(quote.protocol_fee, 299);
+        assert_eq!(quote.end_bin_id, 1);
+        assert_eq!(quote.bins_crossed, 1);
+        assert_eq!(quote.price_impact_bps, 99);
+    }
+
+    #[test]
+    fn exact_out_rounds_input_up() {
+        let mut lb_pair = lb_pair(1);
+        lb_pair.base_free_rate = 10;
+        let bin_array = bin_array(&[(1, 0, 1_000_000)]);
+
+        // 100 Y at 1.01 costs 101 X, plus a fee of 0.1011 rounded up to 1.
+        let quote = quote_exact_out(&lb_pair, &[&bin_array], 100, true, 0).unwrap();
+        assert_eq!(quote.amount_out, 100);
+        assert_eq!(quote.amount_in, 102);
+        assert_eq!(quote.fee, 1);
+
+        // That is the smallest exact-in amount that buys the same output.
+        let exact_in = |amount_in| {
+            quote_exact_in(
+                &lb_pair,
+                &[&bin_array],
+                amount_in,
+                true,
+                SwapLimit::default(),
+                0,
+            )
+            .unwrap()
+            .amount_out
+        };
+        assert_eq!(exact_in(quote.amount_in), 100);
+        assert!(exact_in(quote.amount_in - 1) < 100);
+    }
+
+    #[test]
+    fn exact_out_of_whole_bin_matches_exact_in_maximum() {
+        let mut lb_pair = lb_pair(0);
+        lb_pair.base_free_rate = 10;
+        let bin_array = bin_array(&[(0, 0, 1_000_000)]);
+
+        let quote = quote_exact_out(&lb_pair, &[&bin_array], 1_000_000, true, 0).unwrap();
+        assert_eq!(quote.amount_in, 1_001_002);
+        assert_eq!(quote.fee, 1002);
+    }
+
+    #[test]
+    fn swap_ends_after_stop_bin() {
+        let lb_pair = lb_pair(0);
+        let bin_array = bin_array(&[(0, 0, 1000), (1, 0, 1000), (2, 0, 1000)]);
+        let limit = SwapLimit {
+            stop_bin_id: Some(1),
+            allow_partial_fill: false,
+        };
+
+        let quote = quote_exact_in(&lb_pair, &[&bin_array], 5000, true, limit, 0).unwrap();
+        assert_eq!(quote.amount_out, 2000);
+        assert_eq!(quote.steps.len(), 2);
+        assert_eq!(quote.steps[1].bin_id, 1);
+        // Bin 1 was drained, so the pair is left on the next bin.
+        assert_eq!(quote.end_bin_id, 2);
+
+        let limit = SwapLimit {
+            stop_bin_id: Some(-1),
+            allow_partial_fill: false,
+        };
+        assert_eq!(
+            quote_exact_in(&lb_pair, &[&bin_array], 5000, true, limit, 0).unwrap_err(),
+            ErrorCode::InvalidStopBin.into()
+        );
+    }
+
+    #[test]
+    fn partial_fill_takes_all_liquidity() {
+        let lb_pair = lb_pair(0);
+        let bin_array = bin_array(&[(0, 0, 1000), (1, 0, 1000)]);
+        let limit = SwapLimit {
+            stop_bin_id: None,
+            allow_partial_fill: true,
+        };
+
+        let quote = quote_exact_in(&lb_pair, &[&bin_array], 5000, true, limit, 0).unwrap();
+        assert_eq!(quote.amount_out, 2000);
+        assert_eq!(quote.amount_in, 1000 + 1010);
+        assert_eq!(quote.end_bin_id, 70);
+
+        let quote = quote_exact_in(&lb_pair, &[&bin_array], 500, true, limit, 0).unwrap();
+        assert_eq!(quote.amount_out, 500);
+        assert_eq!(quote.end_bin_id, 0);
+    }
+
+    #[test]
+    fn drained_bin_hands_over_to_next() {
+        let lb_pair = lb_pair(0);
+        let bin_array = bin_array(&[(0, 0, 1000), (1, 0, 1000)]);
+
+        // Exactly draining bin 0 moves the pair up without touching bin 1.
+        let quote =
+            quote_exact_in(&lb_pair, &[&bin_array], 1000, true, SwapLimit::default(), 0).unwrap();
+        assert_eq!(quote.amount_out, 1000);
+        assert_eq!(quote.steps.len(), 1);
+        assert_eq!(quote.end_bin_id, 1);
+
+        // Anything more continues in bin 1.
+        let quote =
+            quote_exact_in(&lb_pair, &[&bin_array], 1101, true, SwapLimit::default(), 0).unwrap();
+        assert_eq!(quote.steps.len(), 2);
+        assert_eq!(quote.steps[1].amount_in, 101);
+        assert_eq!(quote.steps[1].amount_out, 100);
+        assert_eq!(quote.end_bin_id, 1);
+    }
+
+    #[test]
+    fn volatility_follows_filter_and_decay_periods() {
+        let mut lb_pair = lb_pair(5);
+        lb_pair.index_reference = 3;
+        lb_pair.volatility_reference = 10_000;
+        lb_pair.volatility_accumulator = 30_000;
+        lb_pair.last_update_timestamp = 1000;
+        let bin_array = bin_array(&[(5, 0, 1000), (6, 0, 1000)]);
+        let quote_at = |lb_pair: &LbPair, amount_in, timestamp| {
+            quote_exact_in(
+                lb_pair,
+                &[&bin_array],
+                amount_in,
+                true,
+                SwapLimit::default(),
+                timestamp,
+            )
+            .unwrap()
+        };
+
+        // Inside the filter period the old reference is kept.
+        let quote = quote_at(&lb_pair, 10, 1010);
+        assert_eq!(quote.index_reference, 3);
+        assert_eq!(quote.volatility_reference, 10_000);
+        assert_eq!(quote.volatility_accumulator, 30_000);
+
+        // Between filter and decay periods the reference decays by half.
+        let quote = quote_at(&lb_pair, 10, 1100);
+        assert_eq!(quote.index_reference, 5);
+        assert_eq!(quote.volatility_reference, 15_000);
+        assert_eq!(quote.volatility_accumulator, 15_000);
+
+        // After the decay period it resets, and each bin crossed adds 10_000.
+        let quote = quote_at(&lb_pair, 1500, 1600);
+        assert_eq!(quote.index_reference, 5);
+        assert_eq!(quote.volatility_reference, 0);
+        assert_eq!(quote.volatility_accumulator, 10_000);
+
+        // The accumulator never exceeds its maximum.
+        lb_pair.max_volatility_accumulator = 12_000;
+        let quote = quote_at(&lb_pair, 10, 1010);
+        assert_eq!(quote.volatility_accumulator, 12_000);
+    }
+}
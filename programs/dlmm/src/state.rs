@@ -1,4 +1,61 @@
 use anchor_lang::prelude::*;
+use anchor_spl::token::TokenAccount;
+
+use crate::math::{self, mul_div, Rounding, BASIS_POINT_MAX, FEE_PRECISION, MAX_FEE_RATE, ONE};
+
+pub const BINS_PER_ARRAY: usize = 70;
+
+/// Program-wide settings, at `[b"global_config"]`.
+#[account]
+pub struct GlobalConfig {
+    /// Creates presets and withdraws protocol fees.
+    pub admin: Pubkey,
+    /// May pause pairs alongside the admin.
+    pub guardian: Pubkey,
+    /// Presets published so far, at most `MAX_PRESET_PARAMETERS`.
+    pub preset_count: u16,
+    pub bump: u8,
+}
+
+impl GlobalConfig {
+    pub const LEN: usize = 32 + 32 + 2 + 1;
+
+    /// Whether `key` may change a pair's status.
+    pub fn is_operator(&self, key: &Pubkey) -> bool {
+        *key == self.admin || *key == self.guardian
+    }
+}
+
+/// A fee tier. Every pair is created from one and copies its parameters.
+/// Seeded by `[b"preset_parameter", bin_step]`, so each bin step has exactly
+/// one tier, matching the pair seeds.
+#[account]
+pub struct PresetParameter {
+    pub bin_step: u16,
+    /// Basis points, copied to `LbPair::base_free_rate`.
+    pub base_fee_rate: u64,
+    pub filter_period: u16,
+    pub decay_period: u16,
+    pub reduction_factor: u16,
+    pub variable_fee_control: u32,
+    pub max_volatility_accumulator: u64,
+    /// Basis points of each swap fee kept by the protocol.
+    pub protocol_fee_rate: u64,
+    pub bump: u8,
+}
+
+impl PresetParameter {
+    pub const LEN: usize = 2 + 8 + 2 + 2 + 2 + 4 + 8 + 8 + 1;
+}
+
+/// Swaps are rejected.
+pub const PAIR_STATUS_SWAP_DISABLED: u8 = 1 << 0;
+/// New liquidity is rejected.
+pub const PAIR_STATUS_DEPOSIT_DISABLED: u8 = 1 << 1;
+/// Only withdrawals and fee claims go through.
+pub const PAIR_STATUS_WITHDRAW_ONLY: u8 = 1 << 2;
+pub const PAIR_STATUS_MASK: u8 =
+    PAIR_STATUS_SWAP_DISABLED | PAIR_STATUS_DEPOSIT_DISABLED | PAIR_STATUS_WITHDRAW_ONLY;
 
 #[account]
 pub struct LbPair {
@@ -7,16 +64,120 @@ pub struct LbPair {
     pub reserve_x: u64,
     pub reserve_y: u64,
     pub bin_step: u16,
-    pub active_bin_id: u16,
+    pub active_bin_id: i32,
     pub base_free_rate: u64,
     pub protocol_fee_rate: u64,
     pub volatility_accumulator: u64,
     pub last_update_timestamp: u64,
     pub bump: u8,
+    pub volatility_reference: u64,
+    pub index_reference: i32,
+    pub filter_period: u16,
+    pub decay_period: u16,
+    pub reduction_factor: u16,
+    pub variable_fee_control: u32,
+    pub max_volatility_accumulator: u64,
+    pub protocol_fee_x: u64,
+    pub protocol_fee_y: u64,
+    /// Token accounts holding the pair's X and Y, owned by the pair.
+    pub reserve_x_vault: Pubkey,
+    pub reserve_y_vault: Pubkey,
+    /// `PAIR_STATUS_*` flags; zero means fully open.
+    pub status: u8,
 }
 
 impl LbPair {
-    pub const LEN: usize = 8 + 32 + 32 + 8 + 8 + 2 + 2 + 8 + 8 + 8 + 8 + 1;
+    pub const LEN: usize = 8
+        + 32
+        + 32
+        + 8
+        + 8
+        + 2
+        + 4
+        + 8
+        + 8
+        + 8
+        + 8
+        + 1
+        + 8
+        + 4
+        + 2
+        + 2
+        + 2
+        + 4
+        + 8
+        + 8
+        + 8
+        + 64
+        + 1;
+
+    pub fn is_swap_enabled(&self) -> bool {
+        self.status & (PAIR_STATUS_SWAP_DISABLED | PAIR_STATUS_WITHDRAW_ONLY) == 0
+    }
+
+    pub fn is_deposit_enabled(&self) -> bool {
+        self.status & (PAIR_STATUS_DEPOSIT_DISABLED | PAIR_STATUS_WITHDRAW_ONLY) == 0
+    }
+
+    /// Refreshes the volatility reference at the start of a swap. Trades closer
+    /// together than `filter_period` keep building on the same reference, a
+    /// pause shorter than `decay_period` decays it by `reduction_factor`, and a
+    /// longer pause resets it.
+    pub fn update_references(&mut self, current_timestamp: u64) -> Option<()> {
+        let elapsed = current_timestamp.saturating_sub(self.last_update_timestamp);
+
+        if elapsed >= self.filter_period as u64 {
+            self.index_reference = self.active_bin_id;
+            self.volatility_reference = if elapsed < self.decay_period as u64 {
+                self.volatility_accumulator
+                    .checked_mul(self.reduction_factor as u64)?
+                    / BASIS_POINT_MAX
+            } else {
+                0
+            };
+        }
+        Some(())
+    }
+
+    /// Accumulates one `BASIS_POINT_MAX` per bin between `bin_id` and the index reference.
+    pub fn update_volatility_accumulator(&mut self, bin_id: i32) -> Option<()> {
+        let delta_id = (self.index_reference as i64 - bin_id as i64).unsigned_abs();
+        let volatility_accumulator = delta_id
+            .checked_mul(BASIS_POINT_MAX)?
+            .checked_add(self.volatility_reference)?;
+        self.volatility_accumulator = volatility_accumulator.min(self.max_volatility_accumulator);
+        Some(())
+    }
+
+    pub fn base_fee_rate(&self) -> Option<u64> {
+        self.base_free_rate
+            .checked_mul(FEE_PRECISION / BASIS_POINT_MAX)
+    }
+
+    /// Variable fee at the current volatility; see [`math::variable_fee_rate`].
+    pub fn variable_fee_rate(&self) -> Option<u64> {
+        math::variable_fee_rate(
+            self.volatility_accumulator,
+            self.bin_step,
+            self.variable_fee_control,
+        )
+    }
+
+    /// Protocol share of a swap fee; `protocol_fee_rate` is in basis points of the fee.
+    pub fn protocol_fee(&self, fee: u64) -> Option<u64> {
+        let protocol_fee_rate = self.protocol_fee_rate.min(BASIS_POINT_MAX);
+        let protocol_fee =
+            (fee as u128).checked_mul(protocol_fee_rate as u128)? / BASIS_POINT_MAX as u128;
+        u64::try_from(protocol_fee).ok()
+    }
+
+    /// Base plus variable fee, capped at `MAX_FEE_RATE`.
+    pub fn total_fee_rate(&self) -> Option<u64> {
+        let total_fee_rate = self
+            .base_fee_rate()?
+            .checked_add(self.variable_fee_rate()?)?;
+        Some(total_fee_rate.min(MAX_FEE_RATE))
+    }
 }
 
 #[zero_copy]
@@ -27,39 +188,162 @@ pub struct Bin {
     pub fee_y_per_share: u128,
     pub reserve_x: u64,
     pub reserve_y: u64,
-    pub bin_id: u16,
+    pub bin_id: i32,
     pub bump: u8,
-    pub _padding: [u8; 13],
+    pub _padding: [u8; 11],
 }
 
 impl Bin {
-    pub const LEN: usize = 16 + 16 + 16 + 8 + 8 + 2 + 1 + 13;
+    pub const LEN: usize = 16 + 16 + 16 + 8 + 8 + 4 + 1 + 11;
+
+    /// Spreads an LP fee paid in X (`fee_in_x`) or Y over the bin's shares as
+    /// Q64.64 fee growth per share.
+    pub fn credit_fee(&mut self, fee: u64, fee_in_x: bool) -> Option<()> {
+        if fee == 0 || self.total_shares == 0 {
+            return Some(());
+        }
+        let fee_per_share = mul_div(fee as u128, ONE, self.total_shares, Rounding::Down)?;
+        if fee_in_x {
+            self.fee_x_per_share = self.fee_x_per_share.wrapping_add(fee_per_share);
+        } else {
+            self.fee_y_per_share = self.fee_y_per_share.wrapping_add(fee_per_share);
+        }
+        Some(())
+    }
 }
 
 #[account(zero_copy)]
 #[repr(C)]
 pub struct BinArray {
     pub lb_pair: Pubkey,
-    pub index: u16,
+    pub index: i32,
     pub bump: u8,
-    pub _padding: [u8; 13],
+    pub _padding: [u8; 11],
     pub bins: [Bin; 70],
 }
 
 impl BinArray {
-    pub const LEN: usize = 32 + 2 + 1 + 13 + (70 * Bin::LEN);
+    pub const LEN: usize = 32 + 4 + 1 + 11 + (BINS_PER_ARRAY * Bin::LEN);
+
+    /// Index of the bin array covering `bin_id`. Negative ids round towards
+    /// negative infinity, so bin -1 lives in array -1.
+    pub fn bin_array_index(bin_id: i32) -> i32 {
+        bin_id.div_euclid(BINS_PER_ARRAY as i32)
+    }
+
+    /// Lowest bin id covered by the array at `index`.
+    pub fn first_bin_id(index: i32) -> i64 {
+        index as i64 * BINS_PER_ARRAY as i64
+    }
+
+    /// Slot of `bin_id` inside this array, if the array covers it.
+    pub fn bin_index(&self, bin_id: i32) -> Option<usize> {
+        let offset = bin_id as i64 - Self::first_bin_id(self.index);
+        usize::try_from(offset)
+            .ok()
+            .filter(|index| *index < BINS_PER_ARRAY)
+    }
+
+    pub fn bin(&self, bin_id: i32) -> Option<&Bin> {
+        let index = self.bin_index(bin_id)?;
+        Some(&self.bins[index])
+    }
+
+    pub fn bin_mut(&mut self, bin_id: i32) -> Option<&mut Bin> {
+        let index = self.bin_index(bin_id)?;
+        Some(&mut self.bins[index])
+    }
 }
 
-#[account]
+/// A fresh keypair account per position, so an owner can open any number of
+/// them on one pair without sharing a counter. Enumerate an owner's positions
+/// with a `memcmp` filter on `owner`, or on `position_mint` once tokenized.
+#[account(zero_copy)]
+#[repr(C)]
 pub struct Position {
     pub lb_pair: Pubkey,
     pub owner: Pubkey,
     pub lower_bin_id: i32,
     pub upper_bin_id: i32,
+    pub fee_x_pending: u64,
+    pub fee_y_pending: u64,
+    /// 1-of-1 mint representing the position once tokenized, default before.
+    pub position_mint: Pubkey,
+    pub _padding: [u8; 8],
     pub liquidity_shares: [u128; 70],
-    pub bump: u8,
+    pub fee_x_per_share_checkpoint: [u128; 70],
+    pub fee_y_per_share_checkpoint: [u128; 70],
 }
 
 impl Position {
-    pub const LEN: usize = 8 + 32 + 32 + 4 + 4 + 70 * 16 + 1;
+    pub const LEN: usize = 32 + 32 + 4 + 4 + 8 + 8 + 32 + 8 + 3 * (BINS_PER_ARRAY * 16);
+
+    pub fn is_tokenized(&self) -> bool {
+        self.position_mint != Pubkey::default()
+    }
+
+    /// Whether `authority` controls the position: its owner or, once the
+    /// position is tokenized, whoever holds the position token.
+    pub fn is_authorized(&self, authority: &Pubkey, position_token: Option<&TokenAccount>) -> bool {
+        if !self.is_tokenized() {
+            return self.owner == *authority;
+        }
+        position_token.is_some_and(|token| {
+            token.mint == self.position_mint && token.owner == *authority && token.amount == 1
+        })
+    }
+
+    /// Share slot of `bin_id`, counted from `lower_bin_id`, if the position
+    /// covers it.
+    pub fn bin_index(&self, bin_id: i32) -> Option<usize> {
+        if bin_id < self.lower_bin_id || bin_id > self.upper_bin_id {
+            return None;
+        }
+        usize::try_from(bin_id as i64 - self.lower_bin_id as i64).ok()
+    }
+
+    /// Moves the fees `bin` earned on the shares in slot `index` into the
+    /// pending balances and checkpoints the bin's fee growth. Must run before
+    /// the slot's shares change.
+    pub fn update_fees(&mut self, index: usize, bin: &Bin) -> Option<()> {
+        let shares = self.liquidity_shares[index];
+
+        // Fee growth only ever increases, so wrapping differences stay exact.
+        let fee_x_growth = bin
+            .fee_x_per_share
+            .wrapping_sub(self.fee_x_per_share_checkpoint[index]);
+        let fee_y_growth = bin
+            .fee_y_per_share
+            .wrapping_sub(self.fee_y_per_share_checkpoint[index]);
+
+        let fee_x = u64::try_from(mul_div(shares, fee_x_growth, ONE, Rounding::Down)?).ok()?;
+        let fee_y = u64::try_from(mul_div(shares, fee_y_growth, ONE, Rounding::Down)?).ok()?;
+
+        self.fee_x_pending = self.fee_x_pending.checked_add(fee_x)?;
+        self.fee_y_pending = self.fee_y_pending.checked_add(fee_y)?;
+        self.fee_x_per_share_checkpoint[index] = bin.fee_x_per_share;
+        self.fee_y_per_share_checkpoint[index] = bin.fee_y_per_share;
+        Some(())
+    }
+}
+
+/// Most pairs a single token pair can have, one per bin step.
+pub const MAX_PAIRS_PER_REGISTRY: usize = 16;
+
+/// Most presets the admin can publish. A token pair gets at most one pair per
+/// preset, so its registry can never fill up.
+pub const MAX_PRESET_PARAMETERS: usize = MAX_PAIRS_PER_REGISTRY;
+
+/// Every pair created for one token pair, seeded by
+/// `[b"pair_registry", token_x_mint, token_y_mint]`.
+#[account]
+pub struct PairRegistry {
+    pub token_x_mint: Pubkey,
+    pub token_y_mint: Pubkey,
+    pub lb_pairs: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+impl PairRegistry {
+    pub const LEN: usize = 32 + 32 + (4 + 32 * MAX_PAIRS_PER_REGISTRY) + 1;
 }
@@ -0,0 +1,259 @@
+//! Decoders for raw account data as returned by `getAccountInfo`.
+
+use anchor_lang::error::ErrorCode;
+use anchor_lang::prelude::*;
+use anchor_lang::{AccountDeserialize, Discriminator};
+use bytemuck::AnyBitPattern;
+use dlmm::state::{BinArray, GlobalConfig, LbPair, PairRegistry, Position, PresetParameter};
+
+/// Byte offset of `Position::owner` in a position account, for `memcmp`
+/// filters when listing a wallet's untokenized positions.
+pub const POSITION_OWNER_OFFSET: usize = 8 + std::mem::offset_of!(Position, owner);
+
+/// Byte offset of `Position::position_mint`. A token holder finds the position
+/// behind a position token by filtering on the token's mint.
+pub const POSITION_MINT_OFFSET: usize = 8 + std::mem::offset_of!(Position, position_mint);
+
+/// Size of a position account, for the `dataSize` filter that goes with the
+/// `memcmp` filters above.
+pub const POSITION_ACCOUNT_LEN: usize = 8 + Position::LEN;
+
+pub fn decode_global_config(data: &[u8]) -> Result<GlobalConfig> {
+    GlobalConfig::try_deserialize(&mut &data[..])
+}
+
+pub fn decode_preset_parameter(data: &[u8]) -> Result<PresetParameter> {
+    PresetParameter::try_deserialize(&mut &data[..])
+}
+
+pub fn decode_lb_pair(data: &[u8]) -> Result<LbPair> {
+    LbPair::try_deserialize(&mut &data[..])
+}
+
+pub fn decode_pair_registry(data: &[u8]) -> Result<PairRegistry> {
+    PairRegistry::try_deserialize(&mut &data[..])
+}
+
+pub fn decode_bin_array(data: &[u8]) -> Result<BinArray> {
+    decode_zero_copy::<BinArray>(data, BinArray::DISCRIMINATOR)
+}
+
+pub fn decode_position(data: &[u8]) -> Result<Position> {
+    decode_zero_copy::<Position>(data, Position::DISCRIMINATOR)
+}
+
+/// Picks the positions `owner` holds directly out of `getProgramAccounts`
+/// results, usually fetched with a `memcmp` filter of `owner` at
+/// `POSITION_OWNER_OFFSET`. Other accounts are skipped. Tokenized positions
+/// belong to whoever holds their token and are left out; find those by
+/// `position_mint` instead.
+pub fn positions_by_owner(
+    owner: &Pubkey,
+    accounts: &[(Pubkey, Vec<u8>)],
+) -> Vec<(Pubkey, Position)> {
+    accounts
+        .iter()
+        .filter_map(|(address, data)| Some((*address, decode_position(data).ok()?)))
+        .filter(|(_, position)| position.owner == *owner && !position.is_tokenized())
+        .collect()
+}
+
+/// Zero-copy accounts are the discriminator followed by the raw struct.
+/// RPC buffers carry no alignment guarantee, so the struct is copied out.
+fn decode_zero_copy<T: AnyBitPattern>(data: &[u8], discriminator: &[u8]) -> Result<T> {
+    let body = data
+        .strip_prefix(discriminator)
+        .ok_or(ErrorCode::AccountDiscriminatorMismatch)?;
+    let body = body
+        .get(..std::mem::size_of::<T>())
+        .ok_or(ErrorCode::AccountDidNotDeserialize)?;
+    Ok(bytemuck::pod_read_unaligned(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytemuck::Zeroable;
+
+    /// A freshly allocated account: the discriminator followed by `len` zeroes.
+    fn zeroed_account(discriminator: &[u8], len: usize) -> Vec<u8> {
+        let mut data = discriminator.to_vec();
+        data.resize(8 + len, 0);
+        data
+    }
+
+    fn serialize<T: AccountSerialize>(account: &T) -> Vec<u8> {
+        let mut data = Vec::new();
+        account.try_serialize(&mut data).unwrap();
+        data
+    }
+
+    fn zero_copy_account<T: bytemuck::Pod + Discriminator>(account: &T) -> Vec<u8> {
+        [T::DISCRIMINATOR, bytemuck::bytes_of(account)].concat()
+    }
+
+    #[test]
+    fn global_config_round_trips() {
+        let data = zeroed_account(GlobalConfig::DISCRIMINATOR, GlobalConfig::LEN);
+        let mut global_config = decode_global_config(&data).unwrap();
+        assert_eq!(global_config.admin, Pubkey::default());
+
+        global_config.admin = Pubkey::new_unique();
+        global_config.guardian = Pubkey::new_unique();
+        global_config.preset_count = 3;
+        global_config.bump = 255;
+        let decoded = decode_global_config(&serialize(&global_config)).unwrap();
+        assert_eq!(decoded.admin, global_config.admin);
+        assert_eq!(decoded.guardian, global_config.guardian);
+        assert_eq!(decoded.preset_count, 3);
+        assert_eq!(decoded.bump, 255);
+    }
+
+    #[test]
+    fn preset_parameter_round_trips() {
+        let data = zeroed_account(PresetParameter::DISCRIMINATOR, PresetParameter::LEN);
+        let mut preset = decode_preset_parameter(&data).unwrap();
+        assert_eq!(preset.bin_step, 0);
+
+        preset.bin_step = 100;
+        preset.base_fee_rate = 10;
+        preset.variable_fee_control = 7500;
+        preset.max_volatility_accumulator = 150_000;
+        preset.protocol_fee_rate = 2000;
+        let decoded = decode_preset_parameter(&serialize(&preset)).unwrap();
+        assert_eq!(decoded.bin_step, 100);
+        assert_eq!(decoded.base_fee_rate, 10);
+        assert_eq!(decoded.variable_fee_control, 7500);
+        assert_eq!(decoded.max_volatility_accumulator, 150_000);
+        assert_eq!(decoded.protocol_fee_rate, 2000);
+    }
+
+    #[test]
+    fn lb_pair_round_trips() {
+        let data = zeroed_account(LbPair::DISCRIMINATOR, LbPair::LEN);
+        let mut lb_pair = decode_lb_pair(&data).unwrap();
+        assert_eq!(lb_pair.active_bin_id, 0);
+
+        lb_pair.token_x_mint = Pubkey::new_unique();
+        lb_pair.active_bin_id = -42;
+        lb_pair.bin_step = 25;
+        lb_pair.protocol_fee_y = 7;
+        lb_pair.reserve_y_vault = Pubkey::new_unique();
+        lb_pair.status = 4;
+        let decoded = decode_lb_pair(&serialize(&lb_pair)).unwrap();
+        assert_eq!(decoded.token_x_mint, lb_pair.token_x_mint);
+        assert_eq!(decoded.active_bin_id, -42);
+        assert_eq!(decoded.bin_step, 25);
+        assert_eq!(decoded.protocol_fee_y, 7);
+        assert_eq!(decoded.reserve_y_vault, lb_pair.reserve_y_vault);
+        assert_eq!(decoded.status, 4);
+    }
+
+    #[test]
+    fn pair_registry_round_trips() {
+        let data = zeroed_account(PairRegistry::DISCRIMINATOR, PairRegistry::LEN);
+        let mut registry = decode_pair_registry(&data).unwrap();
+        assert!(registry.lb_pairs.is_empty());
+
+        registry.lb_pairs = vec![Pubkey::new_unique(), Pubkey::new_unique()];
+        registry.bump = 254;
+        let decoded = decode_pair_registry(&serialize(&registry)).unwrap();
+        assert_eq!(decoded.lb_pairs, registry.lb_pairs);
+        assert_eq!(decoded.bump, 254);
+    }
+
+    #[test]
+    fn bin_array_round_trips() {
+        let data = zeroed_account(BinArray::DISCRIMINATOR, BinArray::LEN);
+        let decoded = decode_bin_array(&data).unwrap();
+        assert_eq!(decoded.bins[69].bin_id, 0);
+
+        let mut bin_array = BinArray::zeroed();
+        bin_array.lb_pair = Pubkey::new_unique();
+        bin_array.index = -1;
+        bin_array.bins[69].bin_id = -1;
+        bin_array.bins[69].reserve_x = 100_000;
+        bin_array.bins[69].fee_x_per_share = u128::MAX;
+        let decoded = decode_bin_array(&zero_copy_account(&bin_array)).unwrap();
+        assert_eq!(bytemuck::bytes_of(&decoded), bytemuck::bytes_of(&bin_array));
+    }
+
+    #[test]
+    fn position_round_trips() {
+        let data = zeroed_account(Position::DISCRIMINATOR, Position::LEN);
+        let decoded = decode_position(&data).unwrap();
+        assert_eq!(decoded.liquidity_shares[0], 0);
+
+        let mut position = Position::zeroed();
+        position.owner = Pubkey::new_unique();
+        position.position_mint = Pubkey::new_unique();
+        position.lower_bin_id = -1;
+        position.upper_bin_id = 3;
+        position.liquidity_shares[4] = 1 << 100;
+        let data = zero_copy_account(&position);
+        let decoded = decode_position(&data).unwrap();
+        assert_eq!(bytemuck::bytes_of(&decoded), bytemuck::bytes_of(&position));
+
+        // The memcmp offsets point at the fields they name.
+        assert_eq!(
+            &data[POSITION_OWNER_OFFSET..POSITION_OWNER_OFFSET + 32],
+            position.owner.as_ref()
+        );
+        assert_eq!(
+            &data[POSITION_MINT_OFFSET..POSITION_MINT_OFFSET + 32],
+            position.position_mint.as_ref()
+        );
+    }
+
+    #[test]
+    fn zero_copy_decode_handles_unaligned_buffers() {
+        let mut bin_array = BinArray::zeroed();
+        bin_array.index = 7;
+        let mut data = vec![0];
+        data.extend(zero_copy_account(&bin_array));
+
+        let decoded = decode_bin_array(&data[1..]).unwrap();
+        assert_eq!(decoded.index, 7);
+    }
+
+    #[test]
+    fn decoders_reject_other_accounts() {
+        let bin_array = zeroed_account(BinArray::DISCRIMINATOR, BinArray::LEN);
+        assert!(decode_position(&bin_array).is_err());
+        assert!(decode_lb_pair(&bin_array).is_err());
+
+        let lb_pair = zeroed_account(LbPair::DISCRIMINATOR, LbPair::LEN);
+        assert!(decode_bin_array(&lb_pair).is_err());
+        assert!(decode_global_config(&lb_pair).is_err());
+
+        let truncated = &bin_array[..bin_array.len() - 1];
+        assert!(decode_bin_array(truncated).is_err());
+    }
+
+    #[test]
+    fn positions_by_owner_keeps_the_owners_untokenized_positions() {
+        let owner = Pubkey::new_unique();
+        let position = |owner: Pubkey, position_mint: Pubkey| {
+            let mut position = Position::zeroed();
+            position.owner = owner;
+            position.position_mint = position_mint;
+            zero_copy_account(&position)
+        };
+        let own = (Pubkey::new_unique(), position(owner, Pubkey::default()));
+        let other = (
+            Pubkey::new_unique(),
+            position(Pubkey::new_unique(), Pubkey::default()),
+        );
+        let tokenized = (Pubkey::new_unique(), position(owner, Pubkey::new_unique()));
+        let lb_pair = (
+            Pubkey::new_unique(),
+            zeroed_account(LbPair::DISCRIMINATOR, LbPair::LEN),
+        );
+        assert_eq!(own.1.len(), POSITION_ACCOUNT_LEN);
+
+        let found = positions_by_owner(&owner, &[own.clone(), other, tokenized, lb_pair]);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0, own.0);
+        assert_eq!(found[0].1.owner, owner);
+    }
+}
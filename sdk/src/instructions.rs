@@ -0,0 +1,263 @@
+//! One builder per `#[program]` entrypoint.
+//!
+//! Account lists are the program's own `dlmm::accounts::*` structs, so a
+//! builder stops compiling as soon as an instruction's accounts change.
+//! Swaps take the extra bin arrays they may cross, in traversal order, and
+//! the liquidity builders the extra arrays their bins live in.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::{InstructionData, ToAccountMetas};
+use dlmm::accounts;
+use dlmm::instruction as args;
+use dlmm::{
+    BinLiquidityDistribution, BinLiquidityReduction, InitPresetParameterArgs, RebalanceSwap,
+    StrategyParameters,
+};
+
+fn build(
+    accounts: impl ToAccountMetas,
+    data: impl InstructionData,
+    remaining_accounts: &[AccountMeta],
+) -> Instruction {
+    let mut metas = accounts.to_account_metas(None);
+    metas.extend_from_slice(remaining_accounts);
+    Instruction {
+        program_id: dlmm::ID,
+        accounts: metas,
+        data: data.data(),
+    }
+}
+
+fn readonly(keys: &[Pubkey]) -> Vec<AccountMeta> {
+    keys.iter()
+        .map(|key| AccountMeta::new_readonly(*key, false))
+        .collect()
+}
+
+fn writable(keys: &[Pubkey]) -> Vec<AccountMeta> {
+    keys.iter()
+        .map(|key| AccountMeta::new(*key, false))
+        .collect()
+}
+
+pub fn initialize() -> Instruction {
+    build(accounts::Initialize {}, args::Initialize {}, &[])
+}
+
+pub fn initialize_global_config(accounts: accounts::InitializeGlobalConfig) -> Instruction {
+    build(accounts, args::InitializeGlobalConfig {}, &[])
+}
+
+pub fn set_admin(accounts: accounts::SetAdmin) -> Instruction {
+    build(accounts, args::SetAdmin {}, &[])
+}
+
+pub fn set_guardian(accounts: accounts::SetGuardian) -> Instruction {
+    build(accounts, args::SetGuardian {}, &[])
+}
+
+/// `status` is a combination of the `dlmm::state::PAIR_STATUS_*` flags.
+pub fn set_pair_status(accounts: accounts::SetPairStatus, status: u8) -> Instruction {
+    build(accounts, args::SetPairStatus { status }, &[])
+}
+
+pub fn initialize_preset_parameter(
+    accounts: accounts::InitializePresetParameter,
+    preset: InitPresetParameterArgs,
+) -> Instruction {
+    build(
+        accounts,
+        args::InitializePresetParameter { args: preset },
+        &[],
+    )
+}
+
+pub fn initialize_lb_pair(accounts: accounts::InitializeLbPair, active_id: i32) -> Instruction {
+    build(accounts, args::InitializeLbPair { active_id }, &[])
+}
+
+pub fn initialize_bin_array(accounts: accounts::InitializeBinArray, index: i32) -> Instruction {
+    build(accounts, args::InitializeBinArray { index }, &[])
+}
+
+pub fn initialize_position(
+    accounts: accounts::InitializePosition,
+    lower_bin_id: i32,
+    width: u32,
+) -> Instruction {
+    build(
+        accounts,
+        args::InitializePosition {
+            lower_bin_id,
+            width,
+        },
+        &[],
+    )
+}
+
+pub fn transfer_position_owner(accounts: accounts::TransferPositionOwner) -> Instruction {
+    build(accounts, args::TransferPositionOwner {}, &[])
+}
+
+pub fn tokenize_position(accounts: accounts::TokenizePosition) -> Instruction {
+    build(accounts, args::TokenizePosition {}, &[])
+}
+
+pub fn add_liquidity(
+    accounts: accounts::AddLiquidity,
+    bin_arrays: &[Pubkey],
+    amount_x: u64,
+    amount_y: u64,
+    bin_liquidity_dist: Vec<BinLiquidityDistribution>,
+) -> Instruction {
+    build(
+        accounts,
+        args::AddLiquidity {
+            amount_x,
+            amount_y,
+            bin_liquidity_dist,
+        },
+        &writable(bin_arrays),
+    )
+}
+
+pub fn add_liquidity_by_strategy(
+    accounts: accounts::AddLiquidity,
+    bin_arrays: &[Pubkey],
+    amount_x: u64,
+    amount_y: u64,
+    strategy: StrategyParameters,
+) -> Instruction {
+    build(
+        accounts,
+        args::AddLiquidityByStrategy {
+            amount_x,
+            amount_y,
+            strategy,
+        },
+        &writable(bin_arrays),
+    )
+}
+
+pub fn remove_liquidity(
+    accounts: accounts::RemoveLiquidity,
+    bin_arrays: &[Pubkey],
+    bin_liquidity_removal: Vec<BinLiquidityReduction>,
+) -> Instruction {
+    build(
+        accounts,
+        args::RemoveLiquidity {
+            bin_liquidity_removal,
+        },
+        &writable(bin_arrays),
+    )
+}
+
+pub fn remove_liquidity_by_range(
+    accounts: accounts::RemoveLiquidity,
+    bin_arrays: &[Pubkey],
+    from_bin_id: i32,
+    to_bin_id: i32,
+    bps: u16,
+) -> Instruction {
+    build(
+        accounts,
+        args::RemoveLiquidityByRange {
+            from_bin_id,
+            to_bin_id,
+            bps,
+        },
+        &writable(bin_arrays),
+    )
+}
+
+pub fn rebalance_position(
+    accounts: accounts::RemoveLiquidity,
+    bin_arrays: &[Pubkey],
+    bin_liquidity_removal: Vec<BinLiquidityReduction>,
+    new_lower_bin_id: Option<i32>,
+    swap: Option<RebalanceSwap>,
+    bin_liquidity_dist: Vec<BinLiquidityDistribution>,
+) -> Instruction {
+    build(
+        accounts,
+        args::RebalancePosition {
+            bin_liquidity_removal,
+            new_lower_bin_id,
+            swap,
+            bin_liquidity_dist,
+        },
+        &writable(bin_arrays),
+    )
+}
+
+pub fn close_position(accounts: accounts::ClosePosition) -> Instruction {
+    build(accounts, args::ClosePosition {}, &[])
+}
+
+pub fn swap(
+    accounts: accounts::Swap,
+    bin_arrays: &[Pubkey],
+    amount_in: u64,
+    min_amount_out: u64,
+    swap_for_y: bool,
+) -> Instruction {
+    build(
+        accounts,
+        args::Swap {
+            amount_in,
+            min_amount_out,
+            swap_for_y,
+        },
+        &writable(bin_arrays),
+    )
+}
+
+pub fn swap_exact_out(
+    accounts: accounts::Swap,
+    bin_arrays: &[Pubkey],
+    amount_out: u64,
+    max_amount_in: u64,
+    swap_for_y: bool,
+) -> Instruction {
+    build(
+        accounts,
+        args::SwapExactOut {
+            amount_out,
+            max_amount_in,
+            swap_for_y,
+        },
+        &writable(bin_arrays),
+    )
+}
+
+pub fn swap_with_limit(
+    accounts: accounts::Swap,
+    bin_arrays: &[Pubkey],
+    amount_in: u64,
+    min_amount_out: u64,
+    swap_for_y: bool,
+    stop_bin_id: Option<i32>,
+    allow_partial_fill: bool,
+) -> Instruction {
+    build(
+        accounts,
+        args::SwapWithLimit {
+            amount_in,
+            min_amount_out,
+            swap_for_y,
+            stop_bin_id,
+            allow_partial_fill,
+        },
+        &writable(bin_arrays),
+    )
+}
+
+pub fn claim_fee(accounts: accounts::ClaimFee, bin_arrays: &[Pubkey]) -> Instruction {
+    build(accounts, args::ClaimFee {}, &readonly(bin_arrays))
+}
+
+pub fn withdraw_protocol_fee(accounts: accounts::WithdrawProtocolFee) -> Instruction {
+    build(accounts, args::WithdrawProtocolFee {}, &[])
+}
@@ -0,0 +1,13 @@
+//! Off-chain client for the dlmm program: PDA derivation, instruction
+//! builders and account decoders, plus the program's own quote engine.
+//!
+//! Everything here is built on the program crate's types, so a layout or
+//! account change in the program is a compile error here rather than a
+//! silent mismatch.
+
+pub mod accounts;
+pub mod instructions;
+pub mod pda;
+
+pub use dlmm::quote;
+pub use dlmm::ID as PROGRAM_ID;
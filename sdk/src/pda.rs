@@ -0,0 +1,161 @@
+//! Program-derived addresses, mirroring the `seeds` constraints in the program.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::bpf_loader_upgradeable;
+use dlmm::state::BinArray;
+
+/// The program's `ProgramData` account, whose upgrade authority may create the
+/// global config.
+pub fn program_data() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[dlmm::ID.as_ref()], &bpf_loader_upgradeable::ID)
+}
+
+pub fn global_config() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"global_config"], &dlmm::ID)
+}
+
+pub fn preset_parameter(bin_step: u16) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"preset_parameter", &bin_step.to_le_bytes()], &dlmm::ID)
+}
+
+pub fn lb_pair(token_x_mint: &Pubkey, token_y_mint: &Pubkey, bin_step: u16) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"lb_pair",
+            token_x_mint.as_ref(),
+            token_y_mint.as_ref(),
+            &bin_step.to_le_bytes(),
+        ],
+        &dlmm::ID,
+    )
+}
+
+/// Orders two mints the way `initialize_lb_pair` requires, X first.
+pub fn sort_mints(mint_a: Pubkey, mint_b: Pubkey) -> (Pubkey, Pubkey) {
+    if mint_a < mint_b {
+        (mint_a, mint_b)
+    } else {
+        (mint_b, mint_a)
+    }
+}
+
+/// Lists every pair of a token pair; decode it with `accounts::decode_pair_registry`.
+pub fn pair_registry(token_x_mint: &Pubkey, token_y_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"pair_registry",
+            token_x_mint.as_ref(),
+            token_y_mint.as_ref(),
+        ],
+        &dlmm::ID,
+    )
+}
+
+/// Reserve vault holding `mint` for `lb_pair`.
+pub fn reserve(lb_pair: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"reserve", lb_pair.as_ref(), mint.as_ref()], &dlmm::ID)
+}
+
+pub fn bin_array(lb_pair: &Pubkey, index: i32) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"bin_array", lb_pair.as_ref(), &index.to_le_bytes()],
+        &dlmm::ID,
+    )
+}
+
+/// Bin array holding `bin_id`.
+pub fn bin_array_for_bin(lb_pair: &Pubkey, bin_id: i32) -> (Pubkey, u8) {
+    bin_array(lb_pair, BinArray::bin_array_index(bin_id))
+}
+
+pub fn position_mint(position: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"position_mint", position.as_ref()], &dlmm::ID)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checks `derived` against the program's seeds written out byte by byte.
+    fn assert_pda(derived: (Pubkey, u8), seeds: &[&[u8]]) {
+        assert_eq!(derived, Pubkey::find_program_address(seeds, &dlmm::ID));
+        let mut seeds_with_bump = seeds.to_vec();
+        let bump = [derived.1];
+        seeds_with_bump.push(&bump);
+        assert_eq!(
+            Pubkey::create_program_address(&seeds_with_bump, &dlmm::ID).unwrap(),
+            derived.0
+        );
+    }
+
+    #[test]
+    fn program_data_is_owned_by_the_upgradeable_loader() {
+        let (address, bump) = program_data();
+        assert_eq!(
+            Pubkey::create_program_address(
+                &[dlmm::ID.as_ref(), &[bump]],
+                &bpf_loader_upgradeable::ID
+            )
+            .unwrap(),
+            address
+        );
+    }
+
+    #[test]
+    fn config_addresses_match_program_seeds() {
+        assert_pda(global_config(), &[b"global_config"]);
+        // Bin step 100 as a little-endian u16.
+        assert_pda(preset_parameter(100), &[b"preset_parameter", &[100, 0]]);
+    }
+
+    #[test]
+    fn pair_addresses_match_program_seeds() {
+        let (token_x_mint, token_y_mint) = sort_mints(Pubkey::new_unique(), Pubkey::new_unique());
+        assert!(token_x_mint < token_y_mint);
+
+        let lb_pair = lb_pair(&token_x_mint, &token_y_mint, 0x0102);
+        assert_pda(
+            lb_pair,
+            &[
+                b"lb_pair",
+                token_x_mint.as_ref(),
+                token_y_mint.as_ref(),
+                &[0x02, 0x01],
+            ],
+        );
+        assert_pda(
+            pair_registry(&token_x_mint, &token_y_mint),
+            &[
+                b"pair_registry",
+                token_x_mint.as_ref(),
+                token_y_mint.as_ref(),
+            ],
+        );
+        assert_pda(
+            reserve(&lb_pair.0, &token_x_mint),
+            &[b"reserve", lb_pair.0.as_ref(), token_x_mint.as_ref()],
+        );
+    }
+
+    #[test]
+    fn bin_array_addresses_match_program_seeds() {
+        let lb_pair = Pubkey::new_unique();
+        assert_pda(
+            bin_array(&lb_pair, -1),
+            &[b"bin_array", lb_pair.as_ref(), &[0xff, 0xff, 0xff, 0xff]],
+        );
+        assert_eq!(bin_array_for_bin(&lb_pair, -1), bin_array(&lb_pair, -1));
+        assert_eq!(bin_array_for_bin(&lb_pair, -70), bin_array(&lb_pair, -1));
+        assert_eq!(bin_array_for_bin(&lb_pair, 69), bin_array(&lb_pair, 0));
+        assert_eq!(bin_array_for_bin(&lb_pair, 70), bin_array(&lb_pair, 1));
+    }
+
+    #[test]
+    fn position_mint_matches_program_seeds() {
+        let position = Pubkey::new_unique();
+        assert_pda(
+            position_mint(&position),
+            &[b"position_mint", position.as_ref()],
+        );
+    }
+}
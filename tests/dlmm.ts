@@ -2,7 +2,7 @@ import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import { Dlmm } from "../target/types/dlmm";
 import { PublicKey, Keypair, SystemProgram, SYSVAR_RENT_PUBKEY } from "@solana/web3.js";
-import { TOKEN_PROGRAM_ID, createMint, createAccount, mintTo, getAccount, getAssociatedTokenAddress, getOrCreateAssociatedTokenAccount } from "@solana/spl-token";
+import { TOKEN_PROGRAM_ID, createMint, createAccount, mintTo, getAccount, getAssociatedTokenAddress } from "@solana/spl-token";
 import { assert } from "chai";
 
 describe("dlmm", () => {
@@ -15,14 +15,19 @@ describe("dlmm", () => {
   let tokenY: PublicKey;
   let userTokenX: PublicKey;
   let userTokenY: PublicKey;
+  let globalConfig: PublicKey;
+  let presetParameter: PublicKey;
+  let pairRegistry: PublicKey;
   let lbPair: PublicKey;
   let reserveX: PublicKey;
   let reserveY: PublicKey;
   let position: PublicKey;
+  let tokenizedPosition: PublicKey;
   let binArray: PublicKey;
 
   const user = Keypair.generate();
   const binStep = 100;
+  const baseFeeRate = 10; // 0.1%
   const activeId = 0;
   const binArrayIndex = 0;
 
@@ -32,6 +37,10 @@ describe("dlmm", () => {
 
     tokenX = await createMint(provider.connection, user, user.publicKey, null, 6);
     tokenY = await createMint(provider.connection, user, user.publicKey, null, 6);
+    // Pairs require the X mint to sort first.
+    if (Buffer.compare(tokenX.toBuffer(), tokenY.toBuffer()) > 0) {
+      [tokenX, tokenY] = [tokenY, tokenX];
+    }
 
     userTokenX = await createAccount(provider.connection, user, tokenX, user.publicKey);
     userTokenY = await createAccount(provider.connection, user, tokenY, user.publicKey);
@@ -39,22 +48,194 @@ describe("dlmm", () => {
     await mintTo(provider.connection, user, tokenX, userTokenX, user, 10_000_000_000); // 10,000 X
     await mintTo(provider.connection, user, tokenY, userTokenY, user, 10_000_000_000); // 10,000 Y
 
+    [globalConfig] = PublicKey.findProgramAddressSync(
+      [Buffer.from("global_config")],
+      program.programId
+    );
+
+    const binStepBuffer = Buffer.alloc(2);
+    binStepBuffer.writeUInt16LE(binStep, 0);
+    [presetParameter] = PublicKey.findProgramAddressSync(
+      [Buffer.from("preset_parameter"), binStepBuffer],
+      program.programId
+    );
+
     const [lbPairPda] = PublicKey.findProgramAddressSync(
-      [Buffer.from("lb_pair"), tokenX.toBuffer(), tokenY.toBuffer()],
+      [Buffer.from("lb_pair"), tokenX.toBuffer(), tokenY.toBuffer(), binStepBuffer],
       program.programId
     );
     lbPair = lbPairPda;
+
+    [pairRegistry] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pair_registry"), tokenX.toBuffer(), tokenY.toBuffer()],
+      program.programId
+    );
+
+    [reserveX] = PublicKey.findProgramAddressSync(
+      [Buffer.from("reserve"), lbPair.toBuffer(), tokenX.toBuffer()],
+      program.programId
+    );
+    [reserveY] = PublicKey.findProgramAddressSync(
+      [Buffer.from("reserve"), lbPair.toBuffer(), tokenY.toBuffer()],
+      program.programId
+    );
   });
 
-  it("Initialize LbPair", async () => {
+  it("Initialize Global Config", async () => {
+    // Only the upgrade authority, the deploying wallet, may create the config.
+    const [programData] = PublicKey.findProgramAddressSync(
+      [program.programId.toBuffer()],
+      new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+    );
+
+    try {
+      await program.methods
+        .initializeGlobalConfig()
+        .accounts({
+          globalConfig: globalConfig,
+          admin: user.publicKey,
+          program: program.programId,
+          programData: programData,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([user])
+        .rpc();
+      assert.fail("Should have failed");
+    } catch (e) {
+      assert.include(e.toString(), "Unauthorized");
+    }
 
     await program.methods
-      .initializeLbPair(binStep)
+      .initializeGlobalConfig()
+      .accounts({
+        globalConfig: globalConfig,
+        admin: provider.wallet.publicKey,
+        program: program.programId,
+        programData: programData,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .rpc();
+
+    // Hand the admin role to the test user for the rest of the suite.
+    await program.methods
+      .setAdmin()
+      .accounts({
+        globalConfig: globalConfig,
+        admin: provider.wallet.publicKey,
+        newAdmin: user.publicKey,
+      } as any)
+      .rpc();
+
+    const account = await program.account.globalConfig.fetch(globalConfig);
+    assert.ok(account.admin.equals(user.publicKey));
+  });
+
+  it("Initialize Preset Parameter", async () => {
+    await program.methods
+      .initializePresetParameter({
+        binStep: binStep,
+        baseFeeRate: new anchor.BN(baseFeeRate),
+        filterPeriod: 30,
+        decayPeriod: 600,
+        reductionFactor: 5000,
+        variableFeeControl: 7500,
+        maxVolatilityAccumulator: new anchor.BN(150_000),
+        protocolFeeRate: new anchor.BN(2000),
+      })
+      .accounts({
+        globalConfig: globalConfig,
+        presetParameter: presetParameter,
+        admin: user.publicKey,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([user])
+      .rpc();
+
+    const account = await program.account.presetParameter.fetch(presetParameter);
+    assert.equal(account.binStep, binStep);
+    assert.equal(account.baseFeeRate.toString(), baseFeeRate.toString());
+
+    const config = await program.account.globalConfig.fetch(globalConfig);
+    assert.equal(config.presetCount, 1);
+  });
+
+  it("Fail: Second preset for the same bin step", async () => {
+    // Pair addresses only carry the bin step, so a bin step has one tier.
+    try {
+      await program.methods
+        .initializePresetParameter({
+          binStep: binStep,
+          baseFeeRate: new anchor.BN(baseFeeRate * 2),
+          filterPeriod: 30,
+          decayPeriod: 600,
+          reductionFactor: 5000,
+          variableFeeControl: 7500,
+          maxVolatilityAccumulator: new anchor.BN(150_000),
+          protocolFeeRate: new anchor.BN(2000),
+        })
+        .accounts({
+          globalConfig: globalConfig,
+          presetParameter: presetParameter,
+          admin: user.publicKey,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([user])
+        .rpc();
+      assert.fail("Should have failed");
+    } catch (e) {
+      // The system program refuses to allocate the existing preset account.
+      assert.include(e.toString(), "custom program error: 0x0");
+    }
+  });
+
+  it("Fail: Preset whose variable fee can exceed the maximum", async () => {
+    const otherBinStep = 1;
+    const otherBinStepBuffer = Buffer.alloc(2);
+    otherBinStepBuffer.writeUInt16LE(otherBinStep, 0);
+    const [otherPreset] = PublicKey.findProgramAddressSync(
+      [Buffer.from("preset_parameter"), otherBinStepBuffer],
+      program.programId
+    );
+
+    try {
+      await program.methods
+        .initializePresetParameter({
+          binStep: otherBinStep,
+          baseFeeRate: new anchor.BN(baseFeeRate),
+          filterPeriod: 30,
+          decayPeriod: 600,
+          reductionFactor: 5000,
+          variableFeeControl: 4_294_967_295,
+          maxVolatilityAccumulator: new anchor.BN("18446744073709551615"),
+          protocolFeeRate: new anchor.BN(2000),
+        })
+        .accounts({
+          globalConfig: globalConfig,
+          presetParameter: otherPreset,
+          admin: user.publicKey,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([user])
+        .rpc();
+      assert.fail("Should have failed");
+    } catch (e) {
+      assert.include(e.toString(), "InvalidVariableFee");
+    }
+  });
+
+  it("Initialize LbPair", async () => {
+    await program.methods
+      .initializeLbPair(activeId)
       .accounts({
         lbPair: lbPair,
+        presetParameter: presetParameter,
+        pairRegistry: pairRegistry,
+        reserveX: reserveX,
+        reserveY: reserveY,
         user: user.publicKey,
         tokenXMint: tokenX,
         tokenYMint: tokenY,
+        tokenProgram: TOKEN_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
       } as any)
       .signers([user])
@@ -64,6 +245,18 @@ describe("dlmm", () => {
     assert.ok(account.tokenXMint.equals(tokenX));
     assert.ok(account.tokenYMint.equals(tokenY));
     assert.equal(account.binStep, binStep);
+    assert.equal(account.activeBinId, activeId);
+    assert.equal(account.baseFreeRate.toString(), baseFeeRate.toString());
+    assert.equal(account.protocolFeeRate.toString(), "2000");
+    assert.ok(account.reserveXVault.equals(reserveX));
+    assert.ok(account.reserveYVault.equals(reserveY));
+
+    const vault = await getAccount(provider.connection, reserveX);
+    assert.ok(vault.owner.equals(lbPair));
+
+    const registry = await program.account.pairRegistry.fetch(pairRegistry);
+    assert.equal(registry.lbPairs.length, 1);
+    assert.ok(registry.lbPairs[0].equals(lbPair));
   });
 
   it("Initialize BinArray", async () => {
@@ -108,33 +301,29 @@ describe("dlmm", () => {
     }
   });
 
-  it("Prepare Reserves", async () => {
-    const rx = await getOrCreateAssociatedTokenAccount(
-      provider.connection,
-      user,
-      tokenX,
-      lbPair,
-      true
-    );
-    reserveX = rx.address;
-
-    const ry = await getOrCreateAssociatedTokenAccount(
-      provider.connection,
-      user,
-      tokenY,
-      lbPair,
-      true
-    );
-    reserveY = ry.address;
+  it("Initialize Position", async () => {
+    const positionKeypair = Keypair.generate();
+    position = positionKeypair.publicKey;
+
+    // Bins -1 to 1.
+    await program.methods
+      .initializePosition(-1, 3)
+      .accounts({
+        lbPair: lbPair,
+        position: position,
+        owner: user.publicKey,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([user, positionKeypair])
+      .rpc();
+
+    const posAccount = await program.account.position.fetch(position);
+    assert.equal(posAccount.lowerBinId, -1);
+    assert.equal(posAccount.upperBinId, 1);
+    assert.ok(posAccount.owner.equals(user.publicKey));
   });
 
   it("Add Liquidity", async () => {
-    const [positionPda] = PublicKey.findProgramAddressSync(
-      [Buffer.from("position"), lbPair.toBuffer(), user.publicKey.toBuffer()],
-      program.programId
-    );
-    position = positionPda;
-
     const amountX = new anchor.BN(1_000_000);
     const amountY = new anchor.BN(1_000_000);
     const binDist = [
@@ -147,13 +336,13 @@ describe("dlmm", () => {
         lbPair: lbPair,
         binArray: binArray,
         position: position,
+        positionToken: null,
         userTokenX: userTokenX,
         userTokenY: userTokenY,
         reserveX: reserveX,
         reserveY: reserveY,
         user: user.publicKey,
         tokenProgram: TOKEN_PROGRAM_ID,
-        systemProgram: SystemProgram.programId,
       } as any)
       .signers([user])
       .rpc();
@@ -171,7 +360,8 @@ describe("dlmm", () => {
 
   it("Remove Liquidity", async () => {
     const posAccount = await program.account.position.fetch(position);
-    const shares = posAccount.liquidityShares[0];
+    // Share slots count from the position's lower bin, -1.
+    const shares = posAccount.liquidityShares[1];
     const halfShares = shares.div(new anchor.BN(2));
 
     const removal = [
@@ -184,6 +374,7 @@ describe("dlmm", () => {
         lbPair: lbPair,
         binArray: binArray,
         position: position,
+        positionToken: null,
         userTokenX: userTokenX,
         userTokenY: userTokenY,
         reserveX: reserveX,
@@ -251,6 +442,615 @@ describe("dlmm", () => {
     assert.ok(bin.reserveY.gt(new anchor.BN(150_000)));
   });
 
+  it("Swap Exact Out", async () => {
+    const amountOut = new anchor.BN(10_000);
+    const maxAmountIn = new anchor.BN(11_000);
+    const swapForY = true;
+
+    const before = await getAccount(provider.connection, userTokenY);
+
+    await program.methods
+      .swapExactOut(amountOut, maxAmountIn, swapForY)
+      .accounts({
+        lbPair: lbPair,
+        binArray: binArray,
+        user: user.publicKey,
+        userXToken: userTokenX,
+        userYToken: userTokenY,
+        reserveX: reserveX,
+        reserveY: reserveY,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      } as any)
+      .signers([user])
+      .rpc();
+
+    const after = await getAccount(provider.connection, userTokenY);
+    assert.equal((after.amount - before.amount).toString(), "10000");
+  });
+
+  it("Swap With Limit", async () => {
+    const amountIn = new anchor.BN(1_000_000);
+    const minAmountOut = new anchor.BN(0);
+    const swapForY = true;
+    const stopBinId = 0;
+
+    const before = await getAccount(provider.connection, userTokenX);
+
+    await program.methods
+      .swapWithLimit(amountIn, minAmountOut, swapForY, stopBinId, true)
+      .accounts({
+        lbPair: lbPair,
+        binArray: binArray,
+        user: user.publicKey,
+        userXToken: userTokenX,
+        userYToken: userTokenY,
+        reserveX: reserveX,
+        reserveY: reserveY,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      } as any)
+      .signers([user])
+      .rpc();
+
+    // Only bin 0's remaining 190050 Y is bought, plus the 0.1% fee on top;
+    // the rest of the input stays with the user.
+    const after = await getAccount(provider.connection, userTokenX);
+    assert.equal((before.amount - after.amount).toString(), "190241");
+
+    const account = await program.account.lbPair.fetch(lbPair);
+    assert.equal(account.activeBinId, 1);
+  });
+
+  it("Claim Fee", async () => {
+    await program.methods
+      .claimFee()
+      .accounts({
+        lbPair: lbPair,
+        binArray: binArray,
+        position: position,
+        positionToken: null,
+        userTokenX: userTokenX,
+        userTokenY: userTokenY,
+        reserveX: reserveX,
+        reserveY: reserveY,
+        user: user.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      } as any)
+      .signers([user])
+      .rpc();
+
+    const posAccount = await program.account.position.fetch(position);
+    assert.equal(posAccount.feeXPending.toString(), "0");
+    assert.equal(posAccount.feeYPending.toString(), "0");
+  });
+
+  it("Withdraw Protocol Fee", async () => {
+    // 20% of every swap fee so far was set aside for the protocol.
+    const before = await program.account.lbPair.fetch(lbPair);
+    assert.ok(before.protocolFeeX.gt(new anchor.BN(0)));
+    assert.ok(before.protocolFeeY.gt(new anchor.BN(0)));
+
+    await program.methods
+      .withdrawProtocolFee()
+      .accounts({
+        lbPair: lbPair,
+        reserveX: reserveX,
+        reserveY: reserveY,
+        treasuryTokenX: userTokenX,
+        treasuryTokenY: userTokenY,
+        globalConfig: globalConfig,
+        authority: user.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      } as any)
+      .signers([user])
+      .rpc();
+
+    const account = await program.account.lbPair.fetch(lbPair);
+    assert.equal(account.protocolFeeX.toString(), "0");
+    assert.equal(account.protocolFeeY.toString(), "0");
+  });
+
+  it("Add Liquidity Across Bin Arrays", async () => {
+    // Active bin is 1 after the limit swap; bin -1 lives in bin array -1.
+    const lowerIndex = -1;
+    const indexBuffer = Buffer.alloc(4);
+    indexBuffer.writeInt32LE(lowerIndex, 0);
+    const [lowerBinArray] = PublicKey.findProgramAddressSync(
+      [Buffer.from("bin_array"), lbPair.toBuffer(), indexBuffer],
+      program.programId
+    );
+
+    await program.methods
+      .initializeBinArray(lowerIndex)
+      .accounts({
+        lbPair: lbPair,
+        user: user.publicKey,
+      })
+      .signers([user])
+      .rpc();
+
+    const amountX = new anchor.BN(200_000);
+    const amountY = new anchor.BN(0);
+    const binDist = [
+      { deltaId: -2, distX: 5000, distY: 0 },
+      { deltaId: -1, distX: 5000, distY: 0 },
+    ];
+
+    await program.methods
+      .addLiquidity(amountX, amountY, binDist)
+      .accounts({
+        lbPair: lbPair,
+        binArray: binArray,
+        position: position,
+        positionToken: null,
+        userTokenX: userTokenX,
+        userTokenY: userTokenY,
+        reserveX: reserveX,
+        reserveY: reserveY,
+        user: user.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      } as any)
+      .remainingAccounts([
+        { pubkey: lowerBinArray, isSigner: false, isWritable: true },
+      ])
+      .signers([user])
+      .rpc();
+
+    const lowerAccount = await program.account.binArray.fetch(lowerBinArray);
+    const bin = lowerAccount.bins[69];
+    assert.equal(bin.binId, -1);
+    assert.equal(bin.reserveX.toString(), "100000");
+    assert.equal(bin.reserveY.toString(), "0");
+
+    const posAccount = await program.account.position.fetch(position);
+    assert.equal(posAccount.liquidityShares[0].toString(), "100000");
+  });
+
+  it("Claim Fee Across Bin Arrays", async () => {
+    // The position now holds shares in bin -1, so bin array -1 is required.
+    const indexBuffer = Buffer.alloc(4);
+    indexBuffer.writeInt32LE(-1, 0);
+    const [lowerBinArray] = PublicKey.findProgramAddressSync(
+      [Buffer.from("bin_array"), lbPair.toBuffer(), indexBuffer],
+      program.programId
+    );
+    const claimAccounts = {
+      lbPair: lbPair,
+      binArray: binArray,
+      position: position,
+      positionToken: null,
+      userTokenX: userTokenX,
+      userTokenY: userTokenY,
+      reserveX: reserveX,
+      reserveY: reserveY,
+      user: user.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    } as any;
+
+    try {
+      await program.methods
+        .claimFee()
+        .accounts(claimAccounts)
+        .signers([user])
+        .rpc();
+      assert.fail("Should have failed without bin array -1");
+    } catch (e) {
+      assert.include(e.toString(), "BinOutOfRange");
+    }
+
+    await program.methods
+      .claimFee()
+      .accounts(claimAccounts)
+      .remainingAccounts([
+        { pubkey: lowerBinArray, isSigner: false, isWritable: false },
+      ])
+      .signers([user])
+      .rpc();
+  });
+
+  it("Add Liquidity By Strategy", async () => {
+    const amountX = new anchor.BN(100_000);
+    const amountY = new anchor.BN(100_000);
+    const strategy = { minBinId: 1, maxBinId: 1, strategyType: { spot: {} } };
+
+    await program.methods
+      .addLiquidityByStrategy(amountX, amountY, strategy)
+      .accounts({
+        lbPair: lbPair,
+        binArray: binArray,
+        position: position,
+        positionToken: null,
+        userTokenX: userTokenX,
+        userTokenY: userTokenY,
+        reserveX: reserveX,
+        reserveY: reserveY,
+        user: user.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      } as any)
+      .signers([user])
+      .rpc();
+
+    // The active bin takes both sides of a single-bin range in full.
+    const baAccount = await program.account.binArray.fetch(binArray);
+    const bin = baAccount.bins[1];
+    assert.equal(bin.reserveX.toString(), "100000");
+    assert.equal(bin.reserveY.toString(), "100000");
+
+    const posAccount = await program.account.position.fetch(position);
+    assert.ok(posAccount.liquidityShares[2].gt(new anchor.BN(0)));
+  });
+
+  it("Add Imbalanced Liquidity To Active Bin", async () => {
+    // Bin 1 holds 100k X and 100k Y, so only 10k of the 50k X is taken.
+    const amountX = new anchor.BN(50_000);
+    const amountY = new anchor.BN(10_000);
+    const binDist = [
+      { deltaId: 0, distX: 10000, distY: 10000 }
+    ];
+
+    const before = await getAccount(provider.connection, userTokenX);
+
+    await program.methods
+      .addLiquidity(amountX, amountY, binDist)
+      .accounts({
+        lbPair: lbPair,
+        binArray: binArray,
+        position: position,
+        positionToken: null,
+        userTokenX: userTokenX,
+        userTokenY: userTokenY,
+        reserveX: reserveX,
+        reserveY: reserveY,
+        user: user.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      } as any)
+      .signers([user])
+      .rpc();
+
+    const after = await getAccount(provider.connection, userTokenX);
+    assert.equal((before.amount - after.amount).toString(), "10000");
+
+    const baAccount = await program.account.binArray.fetch(binArray);
+    const bin = baAccount.bins[1];
+    assert.equal(bin.reserveX.toString(), "110000");
+    assert.equal(bin.reserveY.toString(), "110000");
+  });
+
+  it("Transfer Position Owner", async () => {
+    const newOwner = Keypair.generate();
+    const secondPositionKeypair = Keypair.generate();
+    const secondPosition = secondPositionKeypair.publicKey;
+
+    await program.methods
+      .initializePosition(0, 1)
+      .accounts({
+        lbPair: lbPair,
+        position: secondPosition,
+        owner: user.publicKey,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([user, secondPositionKeypair])
+      .rpc();
+
+    await program.methods
+      .transferPositionOwner()
+      .accounts({
+        position: secondPosition,
+        owner: user.publicKey,
+        newOwner: newOwner.publicKey,
+      } as any)
+      .signers([user])
+      .rpc();
+
+    const posAccount = await program.account.position.fetch(secondPosition);
+    assert.ok(posAccount.owner.equals(newOwner.publicKey));
+
+    // The new owner finds the position by filtering on `Position::owner`.
+    const owned = await program.account.position.all([
+      { memcmp: { offset: 40, bytes: newOwner.publicKey.toBase58() } },
+    ]);
+    assert.equal(owned.length, 1);
+    assert.ok(owned[0].publicKey.equals(secondPosition));
+  });
+
+  it("Tokenize Position", async () => {
+    const tokenizedPositionKeypair = Keypair.generate();
+    tokenizedPosition = tokenizedPositionKeypair.publicKey;
+    const [positionMint] = PublicKey.findProgramAddressSync(
+      [Buffer.from("position_mint"), tokenizedPosition.toBuffer()],
+      program.programId
+    );
+    const positionToken = await getAssociatedTokenAddress(positionMint, user.publicKey);
+
+    await program.methods
+      .initializePosition(0, 1)
+      .accounts({
+        lbPair: lbPair,
+        position: tokenizedPosition,
+        owner: user.publicKey,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([user, tokenizedPositionKeypair])
+      .rpc();
+
+    await program.methods
+      .tokenizePosition()
+      .accounts({
+        position: tokenizedPosition,
+        positionMint: positionMint,
+        ownerPositionToken: positionToken,
+        owner: user.publicKey,
+      } as any)
+      .signers([user])
+      .rpc();
+
+    const tokenAccount = await getAccount(provider.connection, positionToken);
+    assert.equal(tokenAccount.amount.toString(), "1");
+
+    const posAccount = await program.account.position.fetch(tokenizedPosition);
+    assert.ok(posAccount.positionMint.equals(positionMint));
+
+    // A token holder finds the position behind a token by its mint.
+    const held = await program.account.position.all([
+      { memcmp: { offset: 96, bytes: positionMint.toBase58() } },
+    ]);
+    assert.equal(held.length, 1);
+    assert.ok(held[0].publicKey.equals(tokenizedPosition));
+
+    // The holder now proves control with the token account.
+    await program.methods
+      .claimFee()
+      .accounts({
+        lbPair: lbPair,
+        binArray: binArray,
+        position: tokenizedPosition,
+        positionToken: positionToken,
+        userTokenX: userTokenX,
+        userTokenY: userTokenY,
+        reserveX: reserveX,
+        reserveY: reserveY,
+        user: user.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      } as any)
+      .signers([user])
+      .rpc();
+  });
+
+  it("Remove Liquidity By Range", async () => {
+    const indexBuffer = Buffer.alloc(4);
+    indexBuffer.writeInt32LE(-1, 0);
+    const [lowerBinArray] = PublicKey.findProgramAddressSync(
+      [Buffer.from("bin_array"), lbPair.toBuffer(), indexBuffer],
+      program.programId
+    );
+
+    const before = await program.account.position.fetch(position);
+
+    await program.methods
+      .removeLiquidityByRange(-1, 1, 5000)
+      .accounts({
+        lbPair: lbPair,
+        binArray: binArray,
+        position: position,
+        positionToken: null,
+        userTokenX: userTokenX,
+        userTokenY: userTokenY,
+        reserveX: reserveX,
+        reserveY: reserveY,
+        user: user.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      } as any)
+      .remainingAccounts([
+        { pubkey: lowerBinArray, isSigner: false, isWritable: true },
+      ])
+      .signers([user])
+      .rpc();
+
+    const after = await program.account.position.fetch(position);
+    for (let i = 0; i < 3; i++) {
+      const expected = before.liquidityShares[i].sub(before.liquidityShares[i].divn(2));
+      assert.equal(after.liquidityShares[i].toString(), expected.toString());
+    }
+  });
+
+  it("Rebalance Position", async () => {
+    const indexBuffer = Buffer.alloc(4);
+    indexBuffer.writeInt32LE(-1, 0);
+    const [lowerBinArray] = PublicKey.findProgramAddressSync(
+      [Buffer.from("bin_array"), lbPair.toBuffer(), indexBuffer],
+      program.programId
+    );
+
+    // Move everything in bin -1 into bin 0, which also holds only X.
+    const before = await program.account.position.fetch(position);
+    const removal = [
+      { binId: -1, sharesToBurn: before.liquidityShares[0] }
+    ];
+    const binDist = [
+      { deltaId: -1, distX: 10000, distY: 0 }
+    ];
+
+    const userXBefore = await getAccount(provider.connection, userTokenX);
+
+    await program.methods
+      .rebalancePosition(removal, null, null, binDist)
+      .accounts({
+        lbPair: lbPair,
+        binArray: binArray,
+        position: position,
+        positionToken: null,
+        userTokenX: userTokenX,
+        userTokenY: userTokenY,
+        reserveX: reserveX,
+        reserveY: reserveY,
+        user: user.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      } as any)
+      .remainingAccounts([
+        { pubkey: lowerBinArray, isSigner: false, isWritable: true },
+      ])
+      .signers([user])
+      .rpc();
+
+    const after = await program.account.position.fetch(position);
+    assert.equal(after.liquidityShares[0].toString(), "0");
+    assert.ok(after.liquidityShares[1].gt(before.liquidityShares[1]));
+
+    const userXAfter = await getAccount(provider.connection, userTokenX);
+    assert.equal(userXAfter.amount.toString(), userXBefore.amount.toString());
+  });
+
+  it("Close Position", async () => {
+    // The tokenized position never held liquidity and its fees are claimed.
+    const [positionMint] = PublicKey.findProgramAddressSync(
+      [Buffer.from("position_mint"), tokenizedPosition.toBuffer()],
+      program.programId
+    );
+    const positionToken = await getAssociatedTokenAddress(positionMint, user.publicKey);
+
+    await program.methods
+      .closePosition()
+      .accounts({
+        position: tokenizedPosition,
+        positionToken: positionToken,
+        user: user.publicKey,
+      } as any)
+      .signers([user])
+      .rpc();
+
+    const accountInfo = await provider.connection.getAccountInfo(tokenizedPosition);
+    assert.isNull(accountInfo);
+  });
+
+  it("Pause Pair", async () => {
+    const guardian = Keypair.generate();
+    const withdrawOnly = 4;
+
+    await program.methods
+      .setGuardian()
+      .accounts({
+        globalConfig: globalConfig,
+        admin: user.publicKey,
+        newGuardian: guardian.publicKey,
+      } as any)
+      .signers([user])
+      .rpc();
+
+    await program.methods
+      .setPairStatus(withdrawOnly)
+      .accounts({
+        lbPair: lbPair,
+        globalConfig: globalConfig,
+        authority: guardian.publicKey,
+      } as any)
+      .signers([guardian])
+      .rpc();
+
+    try {
+      await program.methods
+        .swap(new anchor.BN(1_000), new anchor.BN(0), true)
+        .accounts({
+          lbPair: lbPair,
+          binArray: binArray,
+          user: user.publicKey,
+          userXToken: userTokenX,
+          userYToken: userTokenY,
+          reserveX: reserveX,
+          reserveY: reserveY,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        } as any)
+        .signers([user])
+        .rpc();
+      assert.fail("Should have failed");
+    } catch (e) {
+      assert.include(e.toString(), "SwapDisabled");
+    }
+
+    try {
+      await program.methods
+        .addLiquidityByStrategy(new anchor.BN(1_000), new anchor.BN(1_000), { minBinId: 1, maxBinId: 1, strategyType: { spot: {} } })
+        .accounts({
+          lbPair: lbPair,
+          binArray: binArray,
+          position: position,
+          positionToken: null,
+          userTokenX: userTokenX,
+          userTokenY: userTokenY,
+          reserveX: reserveX,
+          reserveY: reserveY,
+          user: user.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        } as any)
+        .signers([user])
+        .rpc();
+      assert.fail("Should have failed");
+    } catch (e) {
+      assert.include(e.toString(), "DepositDisabled");
+    }
+
+    // LPs can still take their liquidity out.
+    const before = await program.account.position.fetch(position);
+    const sharesToBurn = before.liquidityShares[1].div(new anchor.BN(10));
+    await program.methods
+      .removeLiquidity([{ binId: 0, sharesToBurn: sharesToBurn }])
+      .accounts({
+        lbPair: lbPair,
+        binArray: binArray,
+        position: position,
+        positionToken: null,
+        userTokenX: userTokenX,
+        userTokenY: userTokenY,
+        reserveX: reserveX,
+        reserveY: reserveY,
+        user: user.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      } as any)
+      .signers([user])
+      .rpc();
+    const after = await program.account.position.fetch(position);
+    assert.equal(after.liquidityShares[1].toString(), before.liquidityShares[1].sub(sharesToBurn).toString());
+
+    await program.methods
+      .setPairStatus(0)
+      .accounts({
+        lbPair: lbPair,
+        globalConfig: globalConfig,
+        authority: user.publicKey,
+      } as any)
+      .signers([user])
+      .rpc();
+
+    const account = await program.account.lbPair.fetch(lbPair);
+    assert.equal(account.status, 0);
+  });
+
+  it("Fail: Add Y below the active bin", async () => {
+    const binDist = [
+      { deltaId: -1, distX: 0, distY: 10000 }
+    ];
+
+    try {
+      await program.methods
+        .addLiquidity(new anchor.BN(0), new anchor.BN(10_000), binDist)
+        .accounts({
+          lbPair: lbPair,
+          binArray: binArray,
+          position: position,
+          positionToken: null,
+          userTokenX: userTokenX,
+          userTokenY: userTokenY,
+          reserveX: reserveX,
+          reserveY: reserveY,
+          user: user.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        } as any)
+        .signers([user])
+        .rpc();
+      assert.fail("Should have failed");
+    } catch (e) {
+      assert.include(e.toString(), "InvalidBinComposition");
+    }
+  });
+
   it("Fail: Remove more liquidity than owned", async () => {
     const removal = [
       { binId: 0, sharesToBurn: new anchor.BN("1000000000000000000") } // Huge amount
@@ -263,6 +1063,7 @@ describe("dlmm", () => {
           lbPair: lbPair,
           binArray: binArray,
           position: position,
+          positionToken: null,
           userTokenX: userTokenX,
           userTokenY: userTokenY,
           reserveX: reserveX,
@@ -277,4 +1078,47 @@ describe("dlmm", () => {
       assert.ok(true);
     }
   });
+
+  it("Fail: Initialize LbPair with mints out of order", async () => {
+    const binStepBuffer = Buffer.alloc(2);
+    binStepBuffer.writeUInt16LE(binStep, 0);
+    const [reversedPair] = PublicKey.findProgramAddressSync(
+      [Buffer.from("lb_pair"), tokenY.toBuffer(), tokenX.toBuffer(), binStepBuffer],
+      program.programId
+    );
+    const [reversedRegistry] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pair_registry"), tokenY.toBuffer(), tokenX.toBuffer()],
+      program.programId
+    );
+    const [reversedReserveX] = PublicKey.findProgramAddressSync(
+      [Buffer.from("reserve"), reversedPair.toBuffer(), tokenY.toBuffer()],
+      program.programId
+    );
+    const [reversedReserveY] = PublicKey.findProgramAddressSync(
+      [Buffer.from("reserve"), reversedPair.toBuffer(), tokenX.toBuffer()],
+      program.programId
+    );
+
+    try {
+      await program.methods
+        .initializeLbPair(activeId)
+        .accounts({
+          lbPair: reversedPair,
+          presetParameter: presetParameter,
+          pairRegistry: reversedRegistry,
+          reserveX: reversedReserveX,
+          reserveY: reversedReserveY,
+          user: user.publicKey,
+          tokenXMint: tokenY,
+          tokenYMint: tokenX,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([user])
+        .rpc();
+      assert.fail("Should have failed");
+    } catch (e) {
+      assert.include(e.toString(), "InvalidTokenOrder");
+    }
+  });
 });